@@ -1,14 +1,14 @@
 mod quadlet;
 mod systemd_unit;
 
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
 
 use self::quadlet::logger::*;
+use self::quadlet::summary::{json_string, RunSummary};
 use self::quadlet::*;
 
 use self::systemd_unit::*;
 
-use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::env;
 
@@ -25,38 +25,70 @@ const QUADLET_VERSION: &str = "0.2.0-dev";
 
 #[derive(Debug, Default, PartialEq)]
 pub(crate) struct CliOptions {
+    check: bool,
     dry_run: bool,
     is_user: bool,
+    no_enable: bool,
     no_kmsg: bool,
     output_path: PathBuf,
+    read_stdin: bool,
+    summary_json: Option<SummaryOutput>,
+    trace_podman_commands: bool,
+    unit_type: Option<QuadletType>,
     verbose: bool,
     version: bool,
+    version_json: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum SummaryOutput {
+    Stderr,
+    File(PathBuf),
 }
 
 fn help() {
     println!(
         "Usage:
 quadlet-rs --version
-quadlet-rs [--dry-run] [--no-kmsg-log] [--user] [-v|--verbose] OUTPUT_DIR [OUTPUT_DIR] [OUTPUT_DIR]
+quadlet-rs --version-json
+quadlet-rs [--dry-run] [--no-enable] [--no-kmsg-log] [--summary-json[=FILE]] [--trace-podman-commands] [--user] [-v|--verbose] OUTPUT_DIR [OUTPUT_DIR] [OUTPUT_DIR]
+quadlet-rs [--dry-run] [--no-enable] [--no-kmsg-log] [--summary-json[=FILE]] [--trace-podman-commands] [--user] [-v|--verbose] --unit-type=TYPE - [OUTPUT_DIR]
+quadlet-rs --check [--summary-json[=FILE]] [--user] [-v|--verbose] [OUTPUT_DIR]
 
 Options:
-    --dry-run      Run in dry-run mode printing debug information
-    --no-kmsg-log  Don't log to kmsg
-    --user         Run as systemd user
-    -v,--verbose   Print debug information
-    --version      Print version information and exit
+    --check           Only convert the units and report problems, without generating service files
+    --dry-run         Run in dry-run mode printing debug information
+    --no-enable       Don't create symlinks for the [Install] section (e.g. WantedBy)
+    --no-kmsg-log     Don't log to kmsg
+    --summary-json[=FILE]
+                      Write a JSON report of the run to stderr (or FILE)
+    --trace-podman-commands
+                      Log the podman commands generated for each unit
+    --unit-type=TYPE  Quadlet type (e.g. container) of the unit read from stdin
+    --user            Run as systemd user
+    -v,--verbose      Print debug information
+    --version         Print version information and exit
+    --version-json    Print version information (incl. podman's) as JSON and exit
+    -                 Read a single unit from stdin instead of the search directories
 "
     );
 }
 
 fn parse_args(args: Vec<String>) -> Result<CliOptions, RuntimeError> {
     let mut cfg = CliOptions {
+        check: false,
         dry_run: false,
         is_user: false,
+        no_enable: false,
         no_kmsg: false,
         output_path: PathBuf::new(),
+        read_stdin: false,
+        summary_json: None,
+        trace_podman_commands: false,
+        unit_type: None,
         verbose: false,
         version: false,
+        version_json: false,
     };
 
     cfg.is_user = args[0].contains("user");
@@ -69,42 +101,103 @@ fn parse_args(args: Vec<String>) -> Result<CliOptions, RuntimeError> {
         iter.next();
         loop {
             match iter.next().map(String::as_str) {
+                Some("-check" | "--check") => cfg.check = true,
                 Some("-dryrun" | "--dry-run") => cfg.dry_run = true,
+                Some("-no-enable" | "--no-enable") => cfg.no_enable = true,
                 Some("-no-kmsg-log" | "--no-kmsg-log") => cfg.no_kmsg = true,
+                Some("-trace-podman-commands" | "--trace-podman-commands") => {
+                    cfg.trace_podman_commands = true
+                }
                 Some("-user" | "--user") => cfg.is_user = true,
                 Some("-verbose" | "--verbose" | "-v") => cfg.verbose = true,
                 Some("-version" | "--version") => cfg.version = true,
+                Some("-version-json" | "--version-json") => cfg.version_json = true,
+                Some("-") => cfg.read_stdin = true,
+                Some("-summary-json" | "--summary-json") => {
+                    cfg.summary_json = Some(SummaryOutput::Stderr)
+                }
+                Some(arg)
+                    if arg.starts_with("-summary-json=") || arg.starts_with("--summary-json=") =>
+                {
+                    let (_, path) = arg.split_once('=').expect("should contain a '='");
+                    cfg.summary_json = Some(SummaryOutput::File(path.into()));
+                }
+                Some(arg) if arg.starts_with("-unit-type=") || arg.starts_with("--unit-type=") => {
+                    let (_, unit_type) = arg.split_once('=').expect("should contain a '='");
+                    cfg.unit_type = Some(unit_type.parse()?);
+                }
                 Some(path) => {
                     cfg.output_path = path.into();
                     // we only need the first path
                     break;
                 }
-                None => return Err(RuntimeError::CliMissingOutputDirectory(cfg)),
+                None => break,
             }
         }
     }
 
+    if cfg.read_stdin && cfg.unit_type.is_none() {
+        return Err(RuntimeError::CliMissingUnitType);
+    }
+
+    if cfg.output_path.as_os_str().is_empty() {
+        return Err(RuntimeError::CliMissingOutputDirectory(cfg));
+    }
+
     Ok(cfg)
 }
 
+fn print_version_and_exit(cfg: &CliOptions) {
+    if cfg.version_json {
+        let podman_version = detect_podman_version(&get_podman_binary());
+        println!("{}", version_json(podman_version.as_deref()));
+        process::exit(0);
+    }
+    if cfg.version {
+        println!("quadlet-rs {}", QUADLET_VERSION);
+        process::exit(0);
+    }
+}
+
+// Returns e.g. "5.2.0" for "podman version 5.2.0"
+fn detect_podman_version(podman: &str) -> Option<String> {
+    let output = process::Command::new(podman)
+        .arg("--version")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .last()
+        .map(str::to_string)
+}
+
+fn version_json(podman_version: Option<&str>) -> String {
+    format!(
+        "{{\"quadlet_rs\": {}, \"podman\": {}}}",
+        json_string(QUADLET_VERSION),
+        podman_version.map_or("null".to_string(), json_string)
+    )
+}
+
 fn validate_args(mut kmsg_logger: KmsgLogger) -> Result<CliOptions, RuntimeError> {
     let args = env::args().collect();
 
     let cfg = match parse_args(args) {
         Ok(cfg) => {
             // short circuit
-            if cfg.version {
-                println!("quadlet-rs {}", QUADLET_VERSION);
-                process::exit(0);
-            }
+            print_version_and_exit(&cfg);
 
             if cfg.dry_run {
                 kmsg_logger.dry_run = true;
             }
             if cfg.verbose || cfg.dry_run {
-                kmsg_logger.debug_enabled = true;
+                kmsg_logger.max_level = log::LevelFilter::Debug;
             }
-            if cfg.no_kmsg || cfg.dry_run {
+            if cfg.no_kmsg || cfg.dry_run || cfg.check {
                 kmsg_logger.kmsg_enabled = false.into();
             }
 
@@ -112,23 +205,20 @@ fn validate_args(mut kmsg_logger: KmsgLogger) -> Result<CliOptions, RuntimeError
         }
         Err(RuntimeError::CliMissingOutputDirectory(cfg)) => {
             // short circuit
-            if cfg.version {
-                println!("quadlet-rs {}", QUADLET_VERSION);
-                process::exit(0)
-            }
+            print_version_and_exit(&cfg);
 
             if cfg.dry_run {
                 kmsg_logger.dry_run = true;
             }
             if cfg.verbose || cfg.dry_run {
-                kmsg_logger.debug_enabled = true;
+                kmsg_logger.max_level = log::LevelFilter::Debug;
             }
-            if cfg.no_kmsg || cfg.dry_run {
+            if cfg.no_kmsg || cfg.dry_run || cfg.check {
                 kmsg_logger.kmsg_enabled = false.into();
             }
 
             // FIXME: DRY the code around
-            if !cfg.dry_run {
+            if !cfg.dry_run && !cfg.check {
                 return Err(RuntimeError::CliMissingOutputDirectory(cfg));
             }
 
@@ -157,6 +247,11 @@ fn load_units_from_dir(
 
     let files = match iterators::UnitFiles::new(source_path) {
         Ok(entries) => entries,
+        // not all search dirs have to exist (e.g. /etc/containers/systemd/users)
+        Err(RuntimeError::Io(_, e)) if e.kind() == io::ErrorKind::NotFound => {
+            debug!("Skipping {source_path:?}: no such directory");
+            return results;
+        }
         Err(e) => {
             results.push(Err(e));
             return results;
@@ -173,7 +268,11 @@ fn load_units_from_dir(
         };
 
         let path = file.path();
-        let name = file.file_name();
+        // e.g. `foo.container.gz` masks `foo.container` and vice versa
+        let name = uncompressed_path(&path)
+            .file_name()
+            .expect("should have a file name")
+            .to_os_string();
 
         if seen.contains(&name) {
             continue;
@@ -183,6 +282,11 @@ fn load_units_from_dir(
 
         let unit = match SystemdUnitFile::load_from_path(&path) {
             Ok(unit) => unit,
+            // e.g. removed since the directory was read
+            Err(e) if e.is_not_found() => {
+                debug!("Skipping {path:?}: no such file");
+                continue;
+            }
             Err(e) => {
                 match e {
                     IoError::Io(e) => {
@@ -206,20 +310,76 @@ fn load_units_from_dir(
     results
 }
 
+fn load_unit_from_reader<R: io::Read>(
+    mut reader: R,
+    quadlet_type: QuadletType,
+) -> Result<QuadletUnitFile, RuntimeError> {
+    // there's no file name to derive the unit and service names from
+    let path = PathBuf::from(format!("stdin.{}", quadlet_type.extension()));
+
+    let mut buf = String::new();
+    reader
+        .read_to_string(&mut buf)
+        .map_err(|e| RuntimeError::Io(format!("Error reading {path:?}"), e))?;
+
+    QuadletUnitFile::load_from_str(&path, &buf, quadlet_type)
+}
+
 fn generate_service_file(service: &mut SystemdUnitFile) -> io::Result<()> {
     let out_filename = service.path();
 
     debug!("Writing {out_filename:?}");
 
-    let out_file = File::create(out_filename)?;
-    let mut writer = BufWriter::new(out_file);
+    write_atomically(out_filename, |writer| {
+        let args_0 = env::args().next().unwrap();
+        write_service(writer, service, &args_0)
+    })
+}
+
+// Writes the service with a header telling where it came from
+fn write_service<W: Write>(
+    writer: &mut W,
+    service: &SystemdUnitFile,
+    generator: &str,
+) -> io::Result<()> {
+    writeln!(writer, "# Automatically generated by {generator}")?;
+    match service.lookup(UNIT_SECTION, "SourcePath") {
+        Some(source_path) => writeln!(
+            writer,
+            "# from {source_path} with quadlet-rs {QUADLET_VERSION}"
+        )?,
+        None => writeln!(writer, "# with quadlet-rs {QUADLET_VERSION}")?,
+    }
 
-    let args_0 = env::args().next().unwrap();
-    writeln!(writer, "# Automatically generated by {args_0}")?;
+    service.write_to(writer)
+}
 
-    service.write_to(&mut writer)?;
+// Writes to a temporary file next to `path` first and only renames it to `path` when `write`
+// succeeded, so systemd never sees a partially written file (e.g. when the disk is full).
+fn write_atomically<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let mut temp_file_name = OsString::from(".");
+    temp_file_name.push(path.file_name().unwrap_or_default());
+    temp_file_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_file_name);
+
+    let result = File::create(&temp_path).and_then(|temp_file| {
+        let mut writer = BufWriter::new(temp_file);
+        write(&mut writer)?;
+        // dropping the writer would ignore errors when flushing, and the contents have to be
+        // on disk before the rename, otherwise a crash could leave an empty file behind
+        writer.into_inner()?.sync_all()
+    });
 
-    Ok(())
+    match result.and_then(|_| fs::rename(&temp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            fs::remove_file(&temp_path).unwrap_or_default();
+            Err(e)
+        }
+    }
 }
 
 // This parses the `Install` section of the unit file and creates the required
@@ -227,6 +387,26 @@ fn generate_service_file(service: &mut SystemdUnitFile) -> io::Result<()> {
 // In a traditional setup this is done by "systemctl enable", but that doesn't
 // work for auto-generated files like these.
 fn enable_service_file(output_path: &Path, service: &SystemdUnitFile) {
+    for (symlink_rel, target) in install_symlinks(service) {
+        let symlink_path = output_path.join(symlink_rel);
+        let symlink_dir = symlink_path.parent().unwrap();
+        if let Err(e) = fs::create_dir_all(symlink_dir) {
+            warn!("Can't create dir {:?}: {e}", symlink_dir.to_str().unwrap());
+            continue;
+        }
+
+        debug!("Creating symlink {symlink_path:?} -> {target:?}");
+        fs::remove_file(&symlink_path).unwrap_or_default(); // overwrite existing symlinks
+        if let Err(e) = os::unix::fs::symlink(target, &symlink_path) {
+            warn!("Failed creating symlink {:?}: {e}", symlink_path.to_str());
+            continue;
+        }
+    }
+}
+
+// Returns the symlinks (relative to the output dir) `enable_service_file()` creates for the
+// `Alias`, `WantedBy` and `RequiredBy` keys of the `Install` section, with their targets.
+fn install_symlinks(service: &SystemdUnitFile) -> Vec<(PathBuf, PathBuf)> {
     let mut symlinks: Vec<PathBuf> = Vec::new();
 
     let mut alias: Vec<PathBuf> = service
@@ -283,37 +463,36 @@ fn enable_service_file(output_path: &Path, service: &SystemdUnitFile) {
 
     // construct relative symlink targets so that <output_path>/<symlink_rel (aka. foo/<service_name>)>
     // links to <output_path>/<service_name>
-    for symlink_rel in symlinks {
-        let mut target = PathBuf::new();
-
-        // At this point the symlinks are all relative, canonicalized
-        // paths, so the number of slashes corresponds to its path depth
-        // i.e. number of slashes == components - 1
-        for _ in 1..symlink_rel.components().count() {
-            target.push("..");
-        }
-        target.push(service.file_name());
-
-        let symlink_path = output_path.join(symlink_rel);
-        let symlink_dir = symlink_path.parent().unwrap();
-        if let Err(e) = fs::create_dir_all(symlink_dir) {
-            warn!("Can't create dir {:?}: {e}", symlink_dir.to_str().unwrap());
-            continue;
-        }
+    symlinks
+        .into_iter()
+        .map(|symlink_rel| {
+            let mut target = PathBuf::new();
+
+            // At this point the symlinks are all relative, canonicalized
+            // paths, so the number of slashes corresponds to its path depth
+            // i.e. number of slashes == components - 1
+            for _ in 1..symlink_rel.components().count() {
+                target.push("..");
+            }
+            target.push(service.file_name());
 
-        debug!("Creating symlink {symlink_path:?} -> {target:?}");
-        fs::remove_file(&symlink_path).unwrap_or_default(); // overwrite existing symlinks
-        if let Err(e) = os::unix::fs::symlink(target, &symlink_path) {
-            warn!("Failed creating symlink {:?}: {e}", symlink_path.to_str());
-            continue;
-        }
-    }
+            (symlink_rel, target)
+        })
+        .collect()
 }
 
 fn main() {
-    let kmsg_logger = KmsgLogger::new();
+    let mut kmsg_logger = KmsgLogger::new();
+    // systemd's way of configuring log levels (e.g. when debugging generators by hand)
+    if let Some(max_level) = env::var("SYSTEMD_LOG_LEVEL")
+        .ok()
+        .as_deref()
+        .and_then(parse_systemd_log_level)
+    {
+        kmsg_logger.max_level = max_level;
+    }
 
-    let cfg = match validate_args(kmsg_logger) {
+    let mut cfg = match validate_args(kmsg_logger) {
         Ok(cfg) => cfg,
         Err(e) => {
             help();
@@ -322,9 +501,17 @@ fn main() {
         }
     };
 
-    let errs = process(cfg);
-    if !errs.is_empty() {
-        for e in errs {
+    let summary_json = cfg.summary_json.take();
+    let summary = process(cfg);
+
+    if let Some(output) = summary_json {
+        if let Err(e) = write_summary(&summary, &output) {
+            error!("Can't write summary to {output:?}: {e}");
+        }
+    }
+
+    if summary.has_errors() {
+        for (_, e) in summary.errors {
             error!("{e}");
         }
         process::exit(1);
@@ -332,8 +519,118 @@ fn main() {
     process::exit(0);
 }
 
-fn process(cfg: CliOptions) -> Vec<RuntimeError> {
-    let mut prev_errors: Vec<RuntimeError> = Vec::new();
+fn write_summary(summary: &RunSummary, output: &SummaryOutput) -> io::Result<()> {
+    let json = summary.to_json();
+
+    match output {
+        SummaryOutput::Stderr => writeln!(io::stderr(), "{json}"),
+        SummaryOutput::File(path) => fs::write(path, format!("{json}\n")),
+    }
+}
+
+fn sort_units(units: &mut [QuadletUnitFile]) {
+    // Key: Extension
+    // Value: Processing order for resource naming dependencies
+    let sorting_priority: HashMap<QuadletType, usize> = HashMap::from([
+        (QuadletType::Artifact, 1),
+        (QuadletType::Container, 4),
+        (QuadletType::Build, 3),
+        (QuadletType::Image, 1),
+        (QuadletType::Kube, 4),
+        (QuadletType::Network, 2),
+        (QuadletType::Pod, 5),
+        (QuadletType::Volume, 2),
+    ]);
+
+    // Sort unit files according to potential inter-dependencies, with Artifact, Image, Volume and
+    // Network units taking precedence over all others.
+    // resulting order: (.artifact | .image) < (.network | .volume) < .build < (.container | .kube) < .pod
+    // use a stable sort, so units with the same name keep the order they were found in
+    units.sort_by(|a, b| {
+        let a_typ = sorting_priority.get(&a.quadlet_type).unwrap_or(&usize::MAX);
+        let b_typ = sorting_priority.get(&b.quadlet_type).unwrap_or(&usize::MAX);
+
+        // break ties by file name to get a deterministic order among units of the same type
+        a_typ
+            .cmp(b_typ)
+            .then_with(|| a.unit_file.file_name().cmp(b.unit_file.file_name()))
+    });
+}
+
+// Drop-in dirs are only looked up for existing units, so the ones without a unit (e.g. after
+// renaming it) would be ignored silently.
+fn warn_about_orphaned_dropins(source_paths: &[PathBuf], units: &[QuadletUnitFile]) {
+    let mut unit_names: HashSet<String> = HashSet::new();
+    for quadlet in units {
+        let unit_file = &quadlet.unit_file;
+        unit_names.insert(unit_file.file_name().to_string_lossy().into_owned());
+        // instances also use the drop-ins of their template
+        if let (Some(template_base), Some(_)) = unit_file.path().file_name_template_parts() {
+            unit_names.insert(format!("{template_base}@.{}", unit_file.unit_type()));
+        }
+    }
+
+    for source_path in source_paths {
+        let Ok(entries) = fs::read_dir(source_path) else {
+            continue;
+        };
+
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let Some(unit_name) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".d"))
+            else {
+                continue;
+            };
+
+            // type drop-in dirs (e.g. `container.d`) have no extension and apply to all units of their type
+            let is_quadlet_dropin_dir = unit_name
+                .rsplit_once('.')
+                .is_some_and(|(_, extension)| extension.parse::<QuadletType>().is_ok());
+            if !path.is_dir() || !is_quadlet_dropin_dir || unit_names.contains(unit_name) {
+                continue;
+            }
+
+            warn!("Ignoring drop-in dir {path:?}, because there's no {unit_name:?} unit");
+        }
+    }
+}
+
+// Logs the commands of the generated service, so they can be inspected without digging through
+// the service file
+fn trace_podman_commands(service: &SystemdUnitFile) {
+    for key in [
+        "ExecStartPre",
+        "ExecStart",
+        "ExecReload",
+        "ExecStop",
+        "ExecStopPost",
+    ] {
+        for command in service.lookup_all_values_raw(SERVICE_SECTION, key) {
+            info!("{:?} {key}={}", service.file_name(), command.raw());
+        }
+    }
+}
+
+// The banner printed before each service in dry runs, followed by comments listing the drop-ins
+// merged into the source unit
+fn dry_run_header(service: &SystemdUnitFile, dropin_paths: &[PathBuf]) -> String {
+    let mut header = format!("---{:?}---\n", service.path());
+    for dropin_path in dropin_paths {
+        header.push_str(&format!("# Drop-in: {dropin_path:?}\n"));
+    }
+    header
+}
+
+fn process(cfg: CliOptions) -> RunSummary {
+    let mut summary = RunSummary::default();
+
+    // every generated command would be broken otherwise
+    if let Err(e) = resolve_podman_binary(env::var("PODMAN").ok(), env::var_os("PATH")) {
+        summary.add_error(None, e);
+        return summary;
+    }
 
     let mut seen = HashSet::new();
 
@@ -346,128 +643,134 @@ fn process(cfg: CliOptions) -> Vec<RuntimeError> {
         .recursive(true)
         .build();
 
-    let mut units: Vec<QuadletUnitFile> = source_paths
-        .iter()
-        .flat_map(|dir| load_units_from_dir(dir.as_path(), &mut seen))
-        .map(|result| match result {
-            Ok(u) => match QuadletUnitFile::from_unit_file(u) {
-                Ok(u) => Ok(u),
-                Err(e) => Err(e),
-            },
-            Err(e) => Err(e),
-        })
-        .filter_map(|result| match result {
-            Ok(u) => Some(u),
+    let mut units: Vec<QuadletUnitFile> = if cfg.read_stdin {
+        let quadlet_type = cfg.unit_type.clone().expect("should have a unit type");
+        debug!("Loading source unit from stdin");
+        match load_unit_from_reader(io::stdin(), quadlet_type) {
+            Ok(unit) => vec![unit],
             Err(e) => {
-                prev_errors.push(e);
-                None
+                summary.add_error(None, e);
+                return summary;
             }
-        })
-        .collect();
+        }
+    } else {
+        source_paths
+            .iter()
+            .flat_map(|dir| load_units_from_dir(dir.as_path(), &mut seen))
+            .map(|result| match result {
+                Ok(u) => match QuadletUnitFile::from_unit_file(u) {
+                    Ok(u) => Ok(u),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            })
+            .filter_map(|result| match result {
+                Ok(u) => Some(u),
+                Err(e) => {
+                    summary.add_error(None, e);
+                    None
+                }
+            })
+            .collect()
+    };
 
     if units.is_empty() {
         // containers/podman/issues/17374: exit cleanly but log that we
         // had nothing to do
         debug!("No files parsed from {:?}", source_paths.dirs());
-        return prev_errors;
-    }
-
-    for quadlet in units.iter_mut() {
-        let _ = quadlet
-            .unit_file
-            .load_dropins_from(source_paths.dirs().iter().map(|d| d.as_path()))
-            .map_err(|e| {
-                prev_errors.push(RuntimeError::Conversion(
-                    format!("failed loading drop-ins for {quadlet:?}"),
-                    e.into(),
-                ))
-            });
+        return summary;
     }
 
-    if !cfg.dry_run {
-        if let Err(e) = fs::create_dir_all(&cfg.output_path) {
-            prev_errors.push(RuntimeError::Io(
-                format!("Can't create dir {:?}", cfg.output_path),
-                e,
-            ));
-            return prev_errors;
+    // Drop-ins merged into each unit, so dry runs can show where values came from
+    let mut merged_dropins: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+
+    // drop-ins are looked up relative to the unit's file name, which stdin doesn't have
+    if !cfg.read_stdin {
+        for quadlet in units.iter_mut() {
+            let _ = quadlet
+                .unit_file
+                .load_dropins_from(source_paths.dirs().iter().map(|d| d.as_path()))
+                .map(|dropin_paths| {
+                    quadlet.normalize_key_case();
+                    merged_dropins.insert(quadlet.unit_file.path().clone(), dropin_paths)
+                })
+                .map_err(|e| {
+                    summary.add_error(
+                        Some(quadlet.unit_file.path()),
+                        RuntimeError::Conversion(
+                            format!("failed loading drop-ins for {quadlet:?}"),
+                            e.into(),
+                        ),
+                    )
+                });
         }
-    }
 
-    // Key: Extension
-    // Value: Processing order for resource naming dependencies
-    let sorting_priority: HashMap<QuadletType, usize> = HashMap::from([
-        (QuadletType::Container, 4),
-        (QuadletType::Build, 3),
-        (QuadletType::Image, 1),
-        (QuadletType::Kube, 4),
-        (QuadletType::Network, 2),
-        (QuadletType::Pod, 5),
-        (QuadletType::Volume, 2),
-    ]);
+        warn_about_orphaned_dropins(source_paths.dirs(), &units);
+    }
 
-    // Sort unit files according to potential inter-dependencies, with Image, Volume and Network
-    // units taking precedence over all others.
-    // resulting order: .image < (.network | .volume) < .build < (.container | .kube) < .pod
-    units.sort_unstable_by(|a, b| {
-        let a_typ = match QuadletType::from_path(a.unit_file.path()) {
-            Ok(typ) => sorting_priority.get(&typ).unwrap_or(&usize::MAX),
-            Err(_) => &usize::MAX,
-        };
-        let b_typ = match QuadletType::from_path(b.unit_file.path()) {
-            Ok(typ) => sorting_priority.get(&typ).unwrap_or(&usize::MAX),
-            Err(_) => &usize::MAX,
-        };
+    if !cfg.dry_run && !cfg.check {
+        if let Err(e) = fs::create_dir_all(&cfg.output_path) {
+            summary.add_error(
+                None,
+                RuntimeError::Io(format!("Can't create dir {:?}", cfg.output_path), e),
+            );
+            return summary;
+        }
+    }
 
-        a_typ.partial_cmp(b_typ).unwrap_or(Ordering::Less)
-    });
+    sort_units(&mut units);
 
     // Generate the PodsInfoMap to allow containers to link to their pods and add themselves to the pod's containers list
     let mut units_info_map = UnitsInfoMap::from_quadlet_units(units.clone());
 
     for quadlet in units {
+        summary.add_unit(&quadlet.quadlet_type);
+
         let unit = &quadlet.unit_file;
-        let service_result = match quadlet.quadlet_type {
-            QuadletType::Build => convert::from_build_unit(unit, &mut units_info_map, cfg.is_user),
-            QuadletType::Container => {
-                warn_if_ambiguous_image_name(unit, CONTAINER_SECTION);
-                convert::from_container_unit(unit, &mut units_info_map, cfg.is_user)
-            }
-            QuadletType::Image => {
-                warn_if_ambiguous_image_name(unit, IMAGE_SECTION);
-                convert::from_image_unit(unit, &mut units_info_map, cfg.is_user)
-            }
-            QuadletType::Kube => convert::from_kube_unit(unit, &mut units_info_map, cfg.is_user),
-            QuadletType::Network => {
-                convert::from_network_unit(unit, &mut units_info_map, cfg.is_user)
+
+        // conversion stops at the first problem, but we want to report all of them
+        if cfg.check {
+            let errors = quadlet.validate();
+            if !errors.is_empty() {
+                for e in errors {
+                    summary.add_error(
+                        Some(unit.path()),
+                        RuntimeError::Conversion(format!("Checking {:?}", unit.path()), e),
+                    );
+                }
+                continue;
             }
-            QuadletType::Pod => convert::from_pod_unit(unit, &mut units_info_map, cfg.is_user),
-            QuadletType::Volume => {
-                warn_if_ambiguous_image_name(unit, VOLUME_SECTION);
-                convert::from_volume_unit(unit, &mut units_info_map, cfg.is_user)
-            } // _ => {
-              //     warn!("Unsupported file type {:?}", unit.path());
-              //     continue;
-              // }
-        };
+        }
+
+        let service_result = quadlet.convert(&mut units_info_map, cfg.is_user);
 
         let mut service = match service_result {
             Ok(service_unit) => service_unit,
             Err(e) => {
-                prev_errors.push(RuntimeError::Conversion(
-                    format!("Converting {:?}", unit.path()),
-                    e,
-                ));
+                summary.add_error(
+                    Some(unit.path()),
+                    RuntimeError::Conversion(format!("Converting {:?}", unit.path()), e),
+                );
                 continue;
             }
         };
 
+        if cfg.trace_podman_commands {
+            trace_podman_commands(&service);
+        }
+
         let mut service_output_path = cfg.output_path.clone();
         service_output_path.push(service.file_name());
         service.path = service_output_path;
 
+        // the units could be converted, that's all we wanted to know
+        if cfg.check {
+            continue;
+        }
+
         if cfg.dry_run {
-            println!("---{:?}---", service.path());
+            let dropin_paths = merged_dropins.remove(unit.path()).unwrap_or_default();
+            print!("{}", dry_run_header(&service, &dropin_paths));
             _ = io::stdout()
                 .write(service.to_string().as_bytes())
                 .expect("should write to STDOUT");
@@ -486,101 +789,917 @@ fn process(cfg: CliOptions) -> Vec<RuntimeError> {
         }
 
         if let Err(e) = generate_service_file(&mut service) {
-            prev_errors.push(RuntimeError::Io(
-                format!("Generatring service file {:?}", service.path()),
-                e,
-            ));
+            summary.add_error(
+                Some(unit.path()),
+                RuntimeError::Io(format!("Generatring service file {:?}", service.path()), e),
+            );
             continue; // NOTE: Go Quadlet doesn't do this, but it probably should
         }
-        enable_service_file(&cfg.output_path, &service);
+        summary.add_generated_file(service.path());
+        if !cfg.no_enable {
+            enable_service_file(&cfg.output_path, &service);
+        }
     }
 
-    prev_errors
+    summary
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    mod parse_args {
+    mod load_units_from_dir {
         use super::*;
 
         #[test]
-        fn fails_with_no_arguments() {
-            let args: Vec<String> = vec!["./quadlet-rs".into()];
+        fn loads_units_from_nested_dirs() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let web_dir = temp_dir.path().join("web");
+            let dropin_dir = temp_dir.path().join("top.container.d");
+            fs::create_dir(&web_dir).expect("cannot create sub dir");
+            fs::create_dir(&dropin_dir).expect("cannot create drop-in dir");
+            fs::write(temp_dir.path().join("top.container"), "[Container]\n").unwrap();
+            fs::write(web_dir.join("app.container"), "[Container]\n").unwrap();
+            // shadowed by the unit with the same name in the parent dir
+            fs::write(web_dir.join("top.container"), "[Container]\n").unwrap();
+            fs::write(dropin_dir.join("hidden.container"), "[Container]\n").unwrap();
+
+            let source_paths = UnitSearchDirs::new(vec![temp_dir.path().into()])
+                .recursive(true)
+                .build();
+            let mut seen = HashSet::new();
+            let units: Vec<SystemdUnitFile> = source_paths
+                .iter()
+                .flat_map(|dir| load_units_from_dir(dir.as_path(), &mut seen))
+                .map(|result| result.unwrap())
+                .collect();
+
+            let paths: Vec<_> = units.iter().map(|u| u.path().clone()).collect();
+            assert_eq!(
+                paths,
+                vec![
+                    temp_dir.path().join("top.container"),
+                    web_dir.join("app.container")
+                ]
+            );
+        }
+    }
 
-            assert!(matches!(
-                parse_args(args),
-                Err(RuntimeError::CliMissingOutputDirectory(_))
-            ));
+    mod warn_about_orphaned_dropins {
+        use super::*;
+        use crate::quadlet::logger::test_logger::capture_logs;
+
+        fn warnings(source_dir: &Path) -> Vec<String> {
+            let units: Vec<QuadletUnitFile> = load_units_from_dir(source_dir, &mut HashSet::new())
+                .into_iter()
+                .map(|u| QuadletUnitFile::from_unit_file(u.unwrap()).unwrap())
+                .collect();
+
+            let (_, logs) =
+                capture_logs(|| warn_about_orphaned_dropins(&[source_dir.to_path_buf()], &units));
+
+            logs.into_iter()
+                .filter(|(level, _)| *level == log::Level::Warn)
+                .map(|(_, message)| message)
+                .collect()
         }
 
         #[test]
-        fn parses_user_invocation_from_arg_0() {
-            let args: Vec<String> =
-                vec!["./quadlet-rs-user-generator".into(), "./output_dir".into()];
+        fn warns_about_dropin_dir_without_unit() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let dropin_dir = temp_dir.path().join("foo.container.d");
+            fs::create_dir(&dropin_dir).expect("cannot create drop-in dir");
+            fs::write(dropin_dir.join("10-foo.conf"), "[Container]\n").unwrap();
+            fs::write(temp_dir.path().join("bar.container"), "[Container]\n").unwrap();
 
             assert_eq!(
-                parse_args(args).ok().unwrap(),
-                CliOptions {
-                    is_user: true,
-                    output_path: "./output_dir".into(),
-                    ..Default::default()
-                }
+                warnings(temp_dir.path()),
+                vec![format!(
+                    "Ignoring drop-in dir {dropin_dir:?}, because there's no \"foo.container\" unit"
+                )]
             );
         }
 
         #[test]
-        fn accepts_dry_run() {
-            let args: Vec<String> = vec![
-                "./quadlet-rs".into(),
-                "--dry-run".into(),
-                "./output_dir".into(),
-            ];
+        fn ignores_dropin_dirs_with_units() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            for dir in [
+                "foo.container.d",
+                "bar@.container.d",
+                "container.d",
+                "foo.service.d",
+            ] {
+                fs::create_dir(temp_dir.path().join(dir)).expect("cannot create drop-in dir");
+            }
+            fs::write(temp_dir.path().join("foo.container"), "[Container]\n").unwrap();
+            fs::write(temp_dir.path().join("bar@baz.container"), "[Container]\n").unwrap();
+
+            assert!(warnings(temp_dir.path()).is_empty());
+        }
+    }
+
+    mod dry_run_header {
+        use super::*;
+
+        #[test]
+        fn prints_only_banner_without_dropins() {
+            let mut service = SystemdUnitFile::new();
+            service.path = PathBuf::from("/out/foo.service");
 
             assert_eq!(
-                parse_args(args).ok().unwrap(),
-                CliOptions {
-                    dry_run: true,
-                    output_path: "./output_dir".into(),
-                    ..Default::default()
-                }
+                dry_run_header(&service, &[]),
+                "---\"/out/foo.service\"---\n"
             );
         }
 
         #[test]
-        fn accepts_single_dash_dry_run_for_quadlet_compat() {
-            let args: Vec<String> = vec![
-                "./quadlet-rs".into(),
-                "-dryrun".into(),
-                "./output_dir".into(),
-            ];
+        fn lists_merged_dropins() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let dropin_dir = temp_dir.path().join("foo.container.d");
+            fs::create_dir(&dropin_dir).expect("cannot create drop-in dir");
+            fs::write(dropin_dir.join("10-foo.conf"), "[Container]\nLabel=a\n").unwrap();
+            fs::write(dropin_dir.join("20-bar.conf"), "[Container]\nLabel=b\n").unwrap();
+            let unit_path = temp_dir.path().join("foo.container");
+            fs::write(&unit_path, "[Container]\nImage=foo\n").unwrap();
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&unit_path).unwrap();
+            let dropin_paths = unit_file.load_dropins_from([temp_dir.path()]).unwrap();
+            let mut service = SystemdUnitFile::new();
+            service.path = PathBuf::from("/out/foo.service");
 
             assert_eq!(
-                parse_args(args).ok().unwrap(),
-                CliOptions {
-                    dry_run: true,
-                    output_path: "./output_dir".into(),
-                    ..Default::default()
-                }
+                dry_run_header(&service, &dropin_paths),
+                format!(
+                    "---\"/out/foo.service\"---\n# Drop-in: {:?}\n# Drop-in: {:?}\n",
+                    dropin_dir.join("10-foo.conf"),
+                    dropin_dir.join("20-bar.conf"),
+                )
             );
         }
+    }
 
-        #[test]
-        fn accepts_no_kmsg_log() {
-            let args: Vec<String> = vec![
-                "./quadlet-rs".into(),
-                "--no-kmsg-log".into(),
-                "./output_dir".into(),
-            ];
+    mod write_service {
+        use super::*;
+
+        fn write(service: &SystemdUnitFile) -> String {
+            let mut buf = Vec::new();
+            write_service(&mut buf, service, "./quadlet-rs").unwrap();
+            String::from_utf8(buf).unwrap()
+        }
 
+        #[test]
+        fn starts_with_generator_source_path_and_version() {
+            let unit_file = SystemdUnitFile::load_from_str(
+                Path::new("/etc/containers/systemd/foo.container"),
+                "[Container]\nImage=localhost/foo\n",
+            )
+            .unwrap();
+            let quadlet = QuadletUnitFile::from_unit_file(unit_file).unwrap();
+            let mut units_info_map = UnitsInfoMap::from_quadlet_units(vec![quadlet.clone()]);
+            let service = quadlet.convert(&mut units_info_map, false).unwrap();
+
+            let output = write(&service);
+
+            let mut lines = output.lines();
             assert_eq!(
-                parse_args(args).ok().unwrap(),
-                CliOptions {
-                    no_kmsg: true,
-                    output_path: "./output_dir".into(),
-                    ..Default::default()
-                }
+                lines.next(),
+                Some("# Automatically generated by ./quadlet-rs")
+            );
+            assert_eq!(
+                lines.next(),
+                Some(format!("# from /etc/containers/systemd/foo.container with quadlet-rs {QUADLET_VERSION}").as_str())
+            );
+            assert_eq!(lines.next(), Some("[Unit]"));
+        }
+
+        #[test]
+        fn omits_unknown_source_path() {
+            let mut service = SystemdUnitFile::new();
+            service.add(UNIT_SECTION, "Description", "foo");
+
+            assert_eq!(
+                write(&service),
+                format!(
+                    "# Automatically generated by ./quadlet-rs\n\
+                     # with quadlet-rs {QUADLET_VERSION}\n\
+                     [Unit]\n\
+                     Description=foo\n\n"
+                )
+            );
+        }
+    }
+
+    mod write_atomically {
+        use super::*;
+
+        #[test]
+        fn writes_file() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let path = temp_dir.path().join("foo.service");
+
+            write_atomically(&path, |writer| writer.write_all(b"[Unit]\n")).unwrap();
+
+            assert_eq!(fs::read_to_string(&path).unwrap(), "[Unit]\n");
+            assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+        }
+
+        #[test]
+        fn leaves_no_partial_file_on_write_error() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let path = temp_dir.path().join("foo.service");
+
+            let result = write_atomically(&path, |writer| {
+                writer.write_all(b"[Unit]\n")?;
+                Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+            });
+
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::StorageFull);
+            assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+        }
+
+        #[test]
+        fn keeps_previous_file_on_write_error() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let path = temp_dir.path().join("foo.service");
+            fs::write(&path, "previous").unwrap();
+
+            let result = write_atomically(&path, |writer| {
+                writer.write_all(b"[Unit]\n")?;
+                Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+            });
+
+            assert!(result.is_err());
+            assert_eq!(fs::read_to_string(&path).unwrap(), "previous");
+            assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+        }
+    }
+
+    mod install_symlinks {
+        use super::*;
+
+        fn convert(path: &str, data: &str) -> SystemdUnitFile {
+            let quadlet =
+                QuadletUnitFile::load_from_str(Path::new(path), data, QuadletType::Container)
+                    .unwrap();
+            let mut units_info_map = UnitsInfoMap::from_quadlet_units(vec![quadlet.clone()]);
+
+            quadlet.convert(&mut units_info_map, false).unwrap()
+        }
+
+        #[test]
+        fn links_container_service_into_wanted_by_target() {
+            let service = convert(
+                "foo.container",
+                "[Container]\nImage=localhost/foo\n[Install]\nWantedBy=multi-user.target\n",
+            );
+
+            assert_eq!(
+                service.lookup_all(INSTALL_SECTION, "WantedBy"),
+                vec!["multi-user.target"]
+            );
+            assert_eq!(
+                install_symlinks(&service),
+                vec![(
+                    PathBuf::from("multi-user.target.wants/foo.service"),
+                    PathBuf::from("../foo.service")
+                )]
+            );
+        }
+
+        #[test]
+        fn links_aliases_and_required_by_targets() {
+            let service = convert(
+                "foo.container",
+                "[Container]\nImage=localhost/foo\n[Install]\nAlias=bar.service\nRequiredBy=a.target b.target\n",
+            );
+
+            assert_eq!(
+                install_symlinks(&service),
+                vec![
+                    (PathBuf::from("bar.service"), PathBuf::from("foo.service")),
+                    (
+                        PathBuf::from("a.target.requires/foo.service"),
+                        PathBuf::from("../foo.service")
+                    ),
+                    (
+                        PathBuf::from("b.target.requires/foo.service"),
+                        PathBuf::from("../foo.service")
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn ignores_template_without_default_instance() {
+            let service = convert(
+                "foo@.container",
+                "[Container]\nImage=localhost/foo\n[Install]\nWantedBy=multi-user.target\n",
+            );
+
+            assert_eq!(install_symlinks(&service), vec![]);
+        }
+    }
+
+    mod load_unit_from_reader {
+        use super::*;
+
+        #[test]
+        fn converts_container_from_stdin() {
+            let input = "[Container]\nImage=quay.io/podman/hello\n".as_bytes();
+
+            let quadlet = load_unit_from_reader(input, QuadletType::Container).unwrap();
+            assert_eq!(quadlet.quadlet_type, QuadletType::Container);
+            assert_eq!(quadlet.service_name, "stdin");
+
+            let mut units_info_map = UnitsInfoMap::from_quadlet_units(vec![quadlet.clone()]);
+            let service =
+                convert::from_container_unit(&quadlet.unit_file, &mut units_info_map, false)
+                    .unwrap();
+
+            let exec_start = service.lookup(SERVICE_SECTION, "ExecStart").unwrap();
+            assert!(exec_start.contains("--name systemd-%N"), "{exec_start}");
+            assert!(exec_start.ends_with("quay.io/podman/hello"), "{exec_start}");
+        }
+
+        #[test]
+        fn fails_with_invalid_unit() {
+            let input = "[Container\n".as_bytes();
+
+            assert!(matches!(
+                load_unit_from_reader(input, QuadletType::Container),
+                Err(RuntimeError::Conversion(_, ConversionError::Parsing(_)))
+            ));
+        }
+    }
+
+    mod process {
+        use super::*;
+        use crate::quadlet::logger::test_logger::capture_logs;
+
+        // runs `process` for a single container unit and returns the info messages logged
+        fn process_info_logs(trace_podman_commands: bool) -> Vec<String> {
+            // remember global state
+            let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+            let source_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let output_dir = tempfile::tempdir().expect("cannot create temp dir");
+            fs::write(
+                source_dir.path().join("trace-test.container"),
+                "[Container]\nImage=localhost/foo\n",
+            )
+            .unwrap();
+            env::set_var("QUADLET_UNIT_DIRS", source_dir.path());
+
+            let (summary, logs) = capture_logs(|| {
+                process(CliOptions {
+                    output_path: output_dir.path().into(),
+                    trace_podman_commands,
+                    ..Default::default()
+                })
+            });
+
+            // restore global state
+            match _quadlet_unit_dirs {
+                Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+            }
+
+            assert!(!summary.has_errors(), "{:?}", summary.errors);
+
+            logs.into_iter()
+                .filter(|(level, _)| *level == log::Level::Info)
+                .map(|(_, message)| message)
+                .collect()
+        }
+
+        #[test]
+        #[serial_test::serial]
+        fn takes_keys_of_dropins_regardless_of_their_case() {
+            // remember global state
+            let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+            let source_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let output_dir = tempfile::tempdir().expect("cannot create temp dir");
+            fs::write(
+                source_dir.path().join("foo.container"),
+                "[Container]\nImage=localhost/foo\n",
+            )
+            .unwrap();
+            fs::create_dir(source_dir.path().join("foo.container.d")).unwrap();
+            fs::write(
+                source_dir.path().join("foo.container.d/10-image.conf"),
+                "[Container]\nimage=localhost/bar\n",
+            )
+            .unwrap();
+            env::set_var("QUADLET_UNIT_DIRS", source_dir.path());
+
+            let summary = process(CliOptions {
+                output_path: output_dir.path().into(),
+                ..Default::default()
+            });
+
+            // restore global state
+            match _quadlet_unit_dirs {
+                Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+            }
+
+            assert!(!summary.has_errors(), "{:?}", summary.errors);
+            let service =
+                SystemdUnitFile::load_from_path(&output_dir.path().join("foo.service")).unwrap();
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .ends_with(" localhost/bar"));
+        }
+
+        #[test]
+        #[serial_test::serial]
+        fn lets_dropins_replace_yaml_of_kube_units() {
+            // remember global state
+            let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+            let source_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let output_dir = tempfile::tempdir().expect("cannot create temp dir");
+            fs::write(
+                source_dir.path().join("foo.kube"),
+                "[Kube]\nYaml=/foo.yml\n",
+            )
+            .unwrap();
+            fs::create_dir(source_dir.path().join("foo.kube.d")).unwrap();
+            fs::write(
+                source_dir.path().join("foo.kube.d/10-yaml.conf"),
+                "[Kube]\nYaml=/other.yml\n",
+            )
+            .unwrap();
+            env::set_var("QUADLET_UNIT_DIRS", source_dir.path());
+
+            let summary = process(CliOptions {
+                output_path: output_dir.path().into(),
+                ..Default::default()
+            });
+
+            // restore global state
+            match _quadlet_unit_dirs {
+                Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+            }
+
+            assert!(!summary.has_errors(), "{:?}", summary.errors);
+            let service =
+                SystemdUnitFile::load_from_path(&output_dir.path().join("foo.service")).unwrap();
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .ends_with(" /other.yml"));
+        }
+
+        #[test]
+        #[serial_test::serial]
+        fn checks_units_without_generating_service_files() {
+            // remember global state
+            let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+            let source_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let output_dir = tempfile::tempdir().expect("cannot create temp dir");
+            fs::write(
+                source_dir.path().join("good.container"),
+                "[Container]\nImage=localhost/foo\n[Install]\nWantedBy=default.target\n",
+            )
+            .unwrap();
+            fs::write(source_dir.path().join("good.volume"), "[Volume]\n").unwrap();
+            fs::write(
+                source_dir.path().join("bad.container"),
+                "[Container]\nImage=localhost/foo\nNotify=sometimes\n",
+            )
+            .unwrap();
+            fs::write(
+                source_dir.path().join("bad.network"),
+                "[Network]\nFoo=bar\n",
+            )
+            .unwrap();
+            env::set_var("QUADLET_UNIT_DIRS", source_dir.path());
+
+            let summary = process(CliOptions {
+                check: true,
+                output_path: output_dir.path().join("out"),
+                ..Default::default()
+            });
+
+            // restore global state
+            match _quadlet_unit_dirs {
+                Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+            }
+
+            let mut failed: Vec<_> = summary
+                .errors
+                .iter()
+                .map(|(source, _)| source.clone().unwrap())
+                .collect();
+            failed.sort();
+            assert_eq!(
+                failed,
+                [
+                    source_dir.path().join("bad.container"),
+                    source_dir.path().join("bad.network")
+                ]
+            );
+            assert!(!output_dir.path().join("out").exists());
+        }
+
+        #[test]
+        #[serial_test::serial]
+        fn check_reports_all_problems_of_a_unit() {
+            // remember global state
+            let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+            let source_dir = tempfile::tempdir().expect("cannot create temp dir");
+            fs::write(source_dir.path().join("bad.image"), "[Image]\nFoo=bar\n").unwrap();
+            env::set_var("QUADLET_UNIT_DIRS", source_dir.path());
+
+            let summary = process(CliOptions {
+                check: true,
+                output_path: source_dir.path().join("out"),
+                ..Default::default()
+            });
+
+            // restore global state
+            match _quadlet_unit_dirs {
+                Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+            }
+
+            assert_eq!(summary.errors.len(), 2, "{:?}", summary.errors);
+            assert!(matches!(
+                &summary.errors[0].1,
+                RuntimeError::Conversion(_, ConversionError::EmptyRequiredKey(key, _)) if key == "Image"
+            ));
+            assert!(matches!(
+                &summary.errors[1].1,
+                RuntimeError::Conversion(_, ConversionError::UnknownKey(_))
+            ));
+        }
+
+        #[test]
+        #[serial_test::serial]
+        fn traces_podman_commands() {
+            assert_eq!(
+                process_info_logs(true),
+                [
+                    "\"trace-test.service\" ExecStart=/usr/bin/podman run --name systemd-%N --cidfile=%t/%N.cid --replace --rm --cgroups split --sdnotify=conmon -d localhost/foo",
+                    "\"trace-test.service\" ExecStop=/usr/bin/podman rm -v -f -i --cidfile=%t/%N.cid",
+                    "\"trace-test.service\" ExecStopPost=-/usr/bin/podman rm -v -f -i --cidfile=%t/%N.cid",
+                ]
+            );
+        }
+
+        #[test]
+        #[serial_test::serial]
+        fn doesnt_trace_podman_commands_by_default() {
+            assert!(process_info_logs(false).is_empty());
+        }
+
+        // runs `process` with `QUADLET_UNIT_DIRS` pointing at a dir containing a unit
+        // that's wanted by default.target and returns the output dir
+        fn process_wanted_unit(no_enable: bool) -> tempfile::TempDir {
+            // remember global state
+            let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+            let source_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let output_dir = tempfile::tempdir().expect("cannot create temp dir");
+            fs::write(
+                source_dir.path().join("no-enable-test.container"),
+                "[Container]\nImage=foo\n[Install]\nWantedBy=default.target\n",
+            )
+            .unwrap();
+            env::set_var("QUADLET_UNIT_DIRS", source_dir.path());
+
+            let summary = process(CliOptions {
+                no_enable,
+                output_path: output_dir.path().into(),
+                ..Default::default()
+            });
+
+            // restore global state
+            match _quadlet_unit_dirs {
+                Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+            }
+
+            assert!(!summary.has_errors(), "{:?}", summary.errors);
+            assert!(output_dir.path().join("no-enable-test.service").exists());
+
+            output_dir
+        }
+
+        #[test]
+        #[serial_test::serial]
+        fn creates_install_symlinks() {
+            let output_dir = process_wanted_unit(false);
+
+            assert!(output_dir
+                .path()
+                .join("default.target.wants/no-enable-test.service")
+                .is_symlink());
+        }
+
+        #[test]
+        #[serial_test::serial]
+        fn skips_install_symlinks_with_no_enable() {
+            let output_dir = process_wanted_unit(true);
+
+            assert!(!output_dir.path().join("default.target.wants").exists());
+        }
+
+        #[test]
+        #[serial_test::serial]
+        fn pod_only_starts_containers_with_start_with_pod() {
+            // remember global state
+            let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+            let source_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let output_dir = tempfile::tempdir().expect("cannot create temp dir");
+            fs::write(source_dir.path().join("app.pod"), "[Pod]\n").unwrap();
+            fs::write(
+                source_dir.path().join("web.container"),
+                "[Container]\nImage=localhost/web\nPod=app.pod\n",
+            )
+            .unwrap();
+            fs::write(
+                source_dir.path().join("worker.container"),
+                "[Container]\nImage=localhost/worker\nPod=app.pod\nStartWithPod=false\n",
+            )
+            .unwrap();
+            env::set_var("QUADLET_UNIT_DIRS", source_dir.path());
+
+            let summary = process(CliOptions {
+                output_path: output_dir.path().into(),
+                ..Default::default()
+            });
+
+            // restore global state
+            match _quadlet_unit_dirs {
+                Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+            }
+
+            assert!(!summary.has_errors(), "{:?}", summary.errors);
+            let pod_service =
+                SystemdUnitFile::load_from_path(&output_dir.path().join("app-pod.service"))
+                    .unwrap();
+            for key in ["Wants", "Before"] {
+                let container_services: Vec<String> = pod_service
+                    .lookup_all_strv(UNIT_SECTION, key)
+                    .into_iter()
+                    .filter(|unit| unit.starts_with("web") || unit.starts_with("worker"))
+                    .collect();
+                assert_eq!(container_services, ["web.service"], "{key}");
+            }
+            // the container still belongs to the pod, it just isn't started with it
+            let worker_service =
+                SystemdUnitFile::load_from_path(&output_dir.path().join("worker.service")).unwrap();
+            assert_eq!(
+                worker_service.lookup_all(UNIT_SECTION, "BindsTo"),
+                ["app-pod.service"]
+            );
+        }
+    }
+
+    mod version_json {
+        use super::*;
+        use std::os::unix::fs::PermissionsExt;
+
+        #[test]
+        fn contains_quadlet_and_podman_versions() {
+            assert_eq!(
+                version_json(Some("5.2.0")),
+                format!("{{\"quadlet_rs\": \"{QUADLET_VERSION}\", \"podman\": \"5.2.0\"}}")
+            );
+        }
+
+        #[test]
+        fn uses_null_for_unknown_podman_version() {
+            assert_eq!(
+                version_json(None),
+                format!("{{\"quadlet_rs\": \"{QUADLET_VERSION}\", \"podman\": null}}")
+            );
+        }
+
+        #[test]
+        fn detects_podman_version() {
+            let dir = tempfile::tempdir().unwrap();
+            let podman = dir.path().join("podman");
+            fs::write(&podman, "#!/bin/sh\necho podman version 5.2.0\n").unwrap();
+            fs::set_permissions(&podman, fs::Permissions::from_mode(0o755)).unwrap();
+
+            assert_eq!(detect_podman_version(podman.to_str()), Some("5.2.0".into()));
+            assert_eq!(
+                detect_podman_version(dir.path().join("missing").to_str()),
+                None
+            );
+        }
+    }
+
+    mod sort_units {
+        use super::*;
+
+        fn quadlet(path: &str) -> QuadletUnitFile {
+            let unit_file = SystemdUnitFile::load_from_str(Path::new(path), "").unwrap();
+            QuadletUnitFile::from_unit_file(unit_file).unwrap()
+        }
+
+        #[test]
+        fn orders_by_type_then_file_name() {
+            let mut units = vec![
+                quadlet("c.container"),
+                quadlet("b.pod"),
+                quadlet("a.container"),
+                quadlet("z.image"),
+                quadlet("b.kube"),
+                quadlet("b.container"),
+                quadlet("a.volume"),
+                quadlet("a.network"),
+            ];
+
+            sort_units(&mut units);
+
+            let file_names: Vec<_> = units.iter().map(|q| q.unit_file.file_name()).collect();
+            assert_eq!(
+                file_names,
+                vec![
+                    "z.image",
+                    "a.network",
+                    "a.volume",
+                    "a.container",
+                    "b.container",
+                    "b.kube",
+                    "c.container",
+                    "b.pod",
+                ]
+            );
+        }
+
+        #[test]
+        fn keeps_discovery_order_for_same_file_names() {
+            let mut units = vec![
+                quadlet("/run/containers/systemd/foo.container"),
+                quadlet("/etc/containers/systemd/bar.volume"),
+                quadlet("/etc/containers/systemd/foo.container"),
+                quadlet("/usr/share/containers/systemd/foo.container"),
+            ];
+
+            sort_units(&mut units);
+
+            let paths: Vec<_> = units.iter().map(|q| q.unit_file.path().as_path()).collect();
+            assert_eq!(
+                paths,
+                vec![
+                    Path::new("/etc/containers/systemd/bar.volume"),
+                    Path::new("/run/containers/systemd/foo.container"),
+                    Path::new("/etc/containers/systemd/foo.container"),
+                    Path::new("/usr/share/containers/systemd/foo.container"),
+                ]
+            );
+        }
+
+        #[test]
+        fn is_independent_of_input_order() {
+            let names = ["d.container", "a.container", "c.container", "b.container"];
+            let mut units: Vec<_> = names.iter().map(|n| quadlet(n)).collect();
+            let mut reversed: Vec<_> = names.iter().rev().map(|n| quadlet(n)).collect();
+
+            sort_units(&mut units);
+            sort_units(&mut reversed);
+
+            let file_names: Vec<_> = units.iter().map(|q| q.unit_file.file_name()).collect();
+            assert_eq!(
+                file_names,
+                vec!["a.container", "b.container", "c.container", "d.container"]
+            );
+            assert_eq!(
+                file_names,
+                reversed
+                    .iter()
+                    .map(|q| q.unit_file.file_name())
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    mod parse_args {
+        use super::*;
+
+        #[test]
+        fn fails_with_no_arguments() {
+            let args: Vec<String> = vec!["./quadlet-rs".into()];
+
+            assert!(matches!(
+                parse_args(args),
+                Err(RuntimeError::CliMissingOutputDirectory(_))
+            ));
+        }
+
+        #[test]
+        fn parses_user_invocation_from_arg_0() {
+            let args: Vec<String> =
+                vec!["./quadlet-rs-user-generator".into(), "./output_dir".into()];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    is_user: true,
+                    output_path: "./output_dir".into(),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn accepts_check() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--check".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    check: true,
+                    output_path: "./output_dir".into(),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn bare_check_still_reports_missing_output_dir() {
+            let args: Vec<String> = vec!["./quadlet-rs".into(), "--check".into()];
+
+            match parse_args(args) {
+                Err(RuntimeError::CliMissingOutputDirectory(cfg)) => assert_eq!(
+                    cfg,
+                    CliOptions {
+                        check: true,
+                        ..Default::default()
+                    }
+                ),
+                res => panic!("unexpected result: {res:?}"),
+            }
+        }
+
+        #[test]
+        fn accepts_dry_run() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--dry-run".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    dry_run: true,
+                    output_path: "./output_dir".into(),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn accepts_single_dash_dry_run_for_quadlet_compat() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "-dryrun".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    dry_run: true,
+                    output_path: "./output_dir".into(),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn accepts_no_kmsg_log() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--no-kmsg-log".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    no_kmsg: true,
+                    output_path: "./output_dir".into(),
+                    ..Default::default()
+                }
             );
         }
 
@@ -671,6 +1790,16 @@ mod tests {
             );
         }
 
+        #[test]
+        fn accepts_version_json() {
+            let args: Vec<String> = vec!["./quadlet-rs".into(), "--version-json".into()];
+
+            match parse_args(args) {
+                Err(RuntimeError::CliMissingOutputDirectory(cfg)) => assert!(cfg.version_json),
+                other => panic!("unexpected result: {other:?}"),
+            }
+        }
+
         #[test]
         fn accepts_single_dash_verbose_for_quadlet_compat() {
             let args: Vec<String> = vec![
@@ -703,6 +1832,166 @@ mod tests {
             );
         }
 
+        #[test]
+        fn accepts_no_enable() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--no-enable".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    no_enable: true,
+                    output_path: "./output_dir".into(),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn accepts_trace_podman_commands() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--trace-podman-commands".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    output_path: "./output_dir".into(),
+                    trace_podman_commands: true,
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn accepts_summary_json_to_stderr() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--summary-json".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    output_path: "./output_dir".into(),
+                    summary_json: Some(SummaryOutput::Stderr),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn accepts_summary_json_to_file() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--summary-json=/tmp/summary.json".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    output_path: "./output_dir".into(),
+                    summary_json: Some(SummaryOutput::File("/tmp/summary.json".into())),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn accepts_stdin_with_unit_type() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--unit-type=container".into(),
+                "-".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    output_path: "./output_dir".into(),
+                    read_stdin: true,
+                    unit_type: Some(QuadletType::Container),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn accepts_single_dash_unit_type_for_quadlet_compat() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "-unit-type=volume".into(),
+                "-".into(),
+                "./output_dir".into(),
+            ];
+
+            assert_eq!(
+                parse_args(args).ok().unwrap(),
+                CliOptions {
+                    output_path: "./output_dir".into(),
+                    read_stdin: true,
+                    unit_type: Some(QuadletType::Volume),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn stdin_with_dry_run_still_reports_missing_output_dir() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--dry-run".into(),
+                "--unit-type=pod".into(),
+                "-".into(),
+            ];
+
+            match parse_args(args) {
+                Err(RuntimeError::CliMissingOutputDirectory(cfg)) => assert_eq!(
+                    cfg,
+                    CliOptions {
+                        dry_run: true,
+                        read_stdin: true,
+                        unit_type: Some(QuadletType::Pod),
+                        ..Default::default()
+                    }
+                ),
+                res => panic!("unexpected result: {res:?}"),
+            }
+        }
+
+        #[test]
+        fn stdin_requires_unit_type() {
+            let args: Vec<String> = vec!["./quadlet-rs".into(), "-".into(), "./output_dir".into()];
+
+            assert!(matches!(
+                parse_args(args),
+                Err(RuntimeError::CliMissingUnitType)
+            ));
+        }
+
+        #[test]
+        fn fails_with_unsupported_unit_type() {
+            let args: Vec<String> = vec![
+                "./quadlet-rs".into(),
+                "--unit-type=timer".into(),
+                "-".into(),
+                "./output_dir".into(),
+            ];
+
+            assert!(matches!(
+                parse_args(args),
+                Err(RuntimeError::UnsupportedUnitType(t)) if t == "timer"
+            ));
+        }
+
         #[test]
         fn accepts_one_output_dir() {
             let args: Vec<String> = vec!["./quadlet-rs".into(), "./output_dir".into()];
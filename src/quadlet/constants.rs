@@ -7,6 +7,7 @@ pub const UNIT_DIR_DISTRO: &str = "/usr/share/containers/systemd";
 /// Directory for temporary Quadlet files (sysadmin owned)
 pub const UNIT_DIR_TEMP: &str = "/run/containers/systemd";
 
+pub const ARTIFACT_SECTION: &str    = "Artifact";
 pub const BUILD_SECTION: &str       = "Build";
 pub const CONTAINER_SECTION: &str   = "Container";
 pub const IMAGE_SECTION: &str       = "Image";
@@ -15,6 +16,7 @@ pub const NETWORK_SECTION: &str     = "Network";
 pub const POD_SECTION: &str         = "Pod";
 pub const QUADLET_SECTION: &str     = "Quadlet";
 pub const VOLUME_SECTION: &str      = "Volume";
+pub const X_ARTIFACT_SECTION: &str  = "X-Artifact";
 pub const X_BUILD_SECTION: &str     = "X-Build";
 pub const X_CONTAINER_SECTION: &str = "X-Container";
 pub const X_IMAGE_SECTION: &str     = "X-Image";
@@ -26,10 +28,93 @@ pub const X_VOLUME_SECTION: &str    = "X-Volume";
 
 pub const AUTO_UPDATE_LABEL: &str = "io.containers.autoupdate";
 
-pub static SUPPORTED_EXTENSIONS: [&str; 7] =
-    ["build", "container", "image", "kube", "network", "pod", "volume"];
+/// Values of `AutoUpdate=` that `podman auto-update` knows about (`image` is a deprecated alias for `registry`)
+pub static SUPPORTED_AUTO_UPDATE_POLICIES: [&str; 4] = ["disabled", "image", "local", "registry"];
 
-pub static SUPPORTED_BUILD_KEYS: [&str; 24] = [
+/// Keys in the `[Unit]` section that may reference other Quadlet units
+pub static UNIT_DEPENDENCY_KEYS: [&str; 11] = [
+    "After",
+    "Before",
+    "BindsTo",
+    "Conflicts",
+    "OnFailure",
+    "OnSuccess",
+    "PartOf",
+    "Requires",
+    "Requisite",
+    "Upholds",
+    "Wants",
+];
+
+/// Mount types only supported by `podman build` (i.e. `RUN --mount=type=...`)
+pub static UNSUPPORTED_RUN_MOUNT_TYPES: [&str; 1] = ["cache"];
+
+/// Options that can be appended to a `Rootfs=` path (e.g. `/path:O:idmap`)
+pub static SUPPORTED_ROOTFS_OPTIONS: [&str; 2] = ["O", "idmap"];
+
+/// Values accepted by `Pull=` and `Policy=` (i.e. podman's `--pull` or `--policy`)
+pub static SUPPORTED_PULL_POLICIES: [&str; 4] = ["always", "missing", "never", "newer"];
+
+/// Options accepted by `Secret=` in .container files (i.e. `podman run --secret`)
+pub static SUPPORTED_SECRET_OPTIONS: [&str; 6] = ["gid", "mode", "source", "target", "type", "uid"];
+
+/// Options accepted by `Secret=` in .build files (i.e. `podman build --secret`)
+pub static SUPPORTED_BUILD_SECRET_OPTIONS: [&str; 5] = ["env", "id", "source", "src", "type"];
+
+/// Resource names accepted by `Ulimit=` (i.e. `podman run --ulimit`)
+pub static SUPPORTED_ULIMIT_NAMES: [&str; 16] = [
+    "as",
+    "core",
+    "cpu",
+    "data",
+    "fsize",
+    "locks",
+    "memlock",
+    "msgqueue",
+    "nice",
+    "nofile",
+    "nproc",
+    "rss",
+    "rtprio",
+    "rttime",
+    "sigpending",
+    "stack",
+];
+
+/// Values accepted by `SdNotify=` (i.e. `podman run --sdnotify`)
+pub static SUPPORTED_SD_NOTIFY_MODES: [&str; 4] = ["conmon", "container", "healthy", "ignore"];
+
+/// Values accepted by `ExitCodePropagation=` (i.e. `podman kube play --service-exit-code-propagation`)
+pub static SUPPORTED_EXIT_CODE_PROPAGATIONS: [&str; 3] = ["all", "any", "none"];
+
+pub static SUPPORTED_EXTENSIONS: [&str; 8] = [
+    "artifact",
+    "build",
+    "container",
+    "image",
+    "kube",
+    "network",
+    "pod",
+    "volume",
+];
+
+pub static SUPPORTED_ARTIFACT_KEYS: [&str; 13] = [
+    "Artifact",
+    "AuthFile",
+    "CertDir",
+    "ContainersConfModule",
+    "Creds",
+    "DecryptionKey",
+    "GlobalArgs",
+    "PodmanArgs",
+    "Quiet",
+    "Retry",
+    "RetryDelay",
+    "ServiceName",
+    "TLSVerify",
+];
+
+pub static SUPPORTED_BUILD_KEYS: [&str; 25] = [
     "Annotation",
     "Arch",
     "AuthFile",
@@ -50,13 +135,14 @@ pub static SUPPORTED_BUILD_KEYS: [&str; 24] = [
     "Secret",
     "ServiceName",
     "SetWorkingDirectory",
+    "Tag", // alias for ImageTag
     "Target",
     "TLSVerify",
     "Variant",
     "Volume",
 ];
 
-pub static SUPPORTED_CONTAINER_KEYS: [&str; 84] = [
+pub static SUPPORTED_CONTAINER_KEYS: [&str; 86] = [
     "AddCapability",
     "AddDevice",
     "AddHost",
@@ -110,12 +196,14 @@ pub static SUPPORTED_CONTAINER_KEYS: [&str; 84] = [
     "Pull",
     "ReadOnly",
     "ReadOnlyTmpfs",
+    "ReloadCmd",
     "RemapGid",     // deprecated, use UserNS instead
     "RemapUid",     // deprecated, use UserNS instead
     "RemapUidSize", // deprecated, use UserNS instead
     "RemapUsers",   // deprecated, use UserNS instead
     "Rootfs",
     "RunInit",
+    "SdNotify",
     "SeccompProfile",
     "SecurityLabelDisable",
     "SecurityLabelFileType",
@@ -143,7 +231,7 @@ pub static SUPPORTED_CONTAINER_KEYS: [&str; 84] = [
     "WorkingDir",
 ];
 
-pub static SUPPORTED_IMAGE_KEYS: [&str; 15] = [
+pub static SUPPORTED_IMAGE_KEYS: [&str; 16] = [
     "AllTags",
     "Arch",
     "AuthFile",
@@ -156,6 +244,7 @@ pub static SUPPORTED_IMAGE_KEYS: [&str; 15] = [
     "ImageTag",
     "PodmanArgs",
     "OS",
+    "Policy",
     "ServiceName",
     "TLSVerify",
     "Variant",
@@ -229,8 +318,9 @@ pub static SUPPORTED_POD_KEYS: [&str; 24] = [
     "Volume",
 ];
 
-pub static SUPPORTED_QUADLET_KEYS: [&str; 1] = ["DefaultDependencies"];
+pub static SUPPORTED_QUADLET_KEYS: [&str; 2] = ["DefaultDependencies", "PodmanDebug"];
 
+#[allow(dead_code)]
 pub static SUPPORTED_SERVICE_KEYS: [&str; 1] = ["WorkingDirectory"];
 
 pub static SUPPORTED_VOLUME_KEYS: [&str; 14] = [
@@ -1,6 +1,9 @@
-use std::collections::HashMap;
 use std::ffi::OsString;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use log::warn;
 
 use crate::systemd_unit::*;
 
@@ -13,15 +16,152 @@ fn check_for_unknown_keys(
     group_name: &str,
     supported_keys: &[&str],
 ) -> Result<(), ConversionError> {
+    // collect all of them, so they can be fixed in one go
+    let mut unknown_keys: Vec<&str> = Vec::new();
     for (key, _) in unit.section_entries(group_name) {
-        if !supported_keys.contains(&key) {
-            return Err(ConversionError::UnknownKey(format!(
-                "unsupported key '{key}' in group '{group_name}' in {:?}",
-                unit.path()
-            )));
+        // like `QuadletUnitFile::with_type()`, which normalizes the case of supported keys
+        let is_supported = supported_keys.iter().any(|k| k.eq_ignore_ascii_case(key));
+        if !is_supported && !unknown_keys.contains(&key) {
+            unknown_keys.push(key);
+        }
+    }
+
+    if unknown_keys.is_empty() {
+        return Ok(());
+    }
+
+    let plural = if unknown_keys.len() > 1 { "s" } else { "" };
+    let hints: String = unknown_keys
+        .iter()
+        .filter_map(|key| unknown_key_hint(group_name, key))
+        .map(|hint| format!(", {hint}"))
+        .collect();
+    let unknown_keys: Vec<String> = unknown_keys.iter().map(|key| format!("'{key}'")).collect();
+    Err(ConversionError::UnknownKey(format!(
+        "unsupported key{plural} {} in group '{group_name}' in {:?}{hints}",
+        unknown_keys.join(", "),
+        unit.path()
+    )))
+}
+
+// Explains keys that are easily confused with supported keys of another Quadlet type
+fn unknown_key_hint(group_name: &str, key: &str) -> Option<&'static str> {
+    match (group_name, key) {
+        (CONTAINER_SECTION, "SetWorkingDirectory") => Some(
+            "use WorkingDir= for the working directory inside the container (SetWorkingDirectory= sets the host-side working directory of .build and .kube units)",
+        ),
+        _ => None,
+    }
+}
+
+// Runs the checks which need neither other units nor podman commands (e.g. for linting units).
+// Unlike the converters, which stop at the first error, this reports all errors found.
+pub(crate) fn validate_unit(
+    unit: &SystemdUnitFile,
+    quadlet_type: &QuadletType,
+) -> Vec<ConversionError> {
+    let mut errors = Vec::new();
+
+    let required_key_error = match quadlet_type {
+        QuadletType::Artifact => check_required_key(unit, ARTIFACT_SECTION, "Artifact").err(),
+        QuadletType::Build => get_built_image_name(unit)
+            .is_none()
+            .then(|| ConversionError::EmptyRequiredKey("ImageTag".into(), unit.file_name().into())),
+        QuadletType::Container => check_image_or_rootfs(unit).err(),
+        QuadletType::Image => check_required_key(unit, IMAGE_SECTION, "Image").err(),
+        QuadletType::Kube => get_kube_yaml_path(unit).err(),
+        _ => None,
+    };
+    errors.extend(required_key_error);
+
+    errors.extend(
+        check_for_unknown_keys(
+            unit,
+            quadlet_type.section_name(),
+            supported_keys(quadlet_type.clone()),
+        )
+        .err(),
+    );
+    errors.extend(check_for_unknown_keys(unit, QUADLET_SECTION, &SUPPORTED_QUADLET_KEYS).err());
+
+    errors
+}
+
+// Like `validate_unit()`, but only returns the first error
+fn validate(unit: &SystemdUnitFile, quadlet_type: QuadletType) -> Result<(), ConversionError> {
+    match validate_unit(unit, &quadlet_type).into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+fn check_required_key(
+    unit: &SystemdUnitFile,
+    section: &str,
+    key: &str,
+) -> Result<(), ConversionError> {
+    if unit
+        .lookup_last(section, key)
+        .unwrap_or_default()
+        .is_empty()
+    {
+        return Err(ConversionError::EmptyRequiredKey(
+            key.into(),
+            unit.file_name().into(),
+        ));
+    }
+
+    Ok(())
+}
+
+// One image or rootfs must be specified for the container
+fn check_image_or_rootfs(container: &SystemdUnitFile) -> Result<(), ConversionError> {
+    let has_image = !container
+        .lookup_last(CONTAINER_SECTION, "Image")
+        .unwrap_or_default()
+        .is_empty();
+    let has_rootfs = !container
+        .lookup_last(CONTAINER_SECTION, "Rootfs")
+        .unwrap_or_default()
+        .is_empty();
+
+    match (has_image, has_rootfs) {
+        (false, false) => Err(ConversionError::EmptyRequiredKey(
+            "Image or Rootfs".into(),
+            container.file_name().into(),
+        )),
+        (true, true) => Err(ConversionError::MutuallyExclusiveKeys(
+            "Image".into(),
+            "Rootfs".into(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// The last Yaml= wins, e.g. when a drop-in replaces the Yaml= of the unit
+fn get_kube_yaml_path(kube: &SystemdUnitFile) -> Result<String, ConversionError> {
+    kube.lookup_all(KUBE_SECTION, "Yaml")
+        .pop()
+        .ok_or_else(|| ConversionError::EmptyRequiredKey("Yaml".into(), kube.file_name().into()))
+}
+
+// Different Yaml= values in a single file are most likely a mistake. This has to be checked before
+// merging drop-ins, which may replace the Yaml= of the unit. An empty Yaml= resets all previous values.
+pub(crate) fn check_for_conflicting_yaml(kube: &SystemdUnitFile) -> Result<(), ConversionError> {
+    let mut yaml_paths: Vec<String> = Vec::new();
+    for yaml_path in kube.lookup_all(KUBE_SECTION, "Yaml") {
+        if !yaml_paths.contains(&yaml_path) {
+            yaml_paths.push(yaml_path);
         }
     }
 
+    if yaml_paths.len() > 1 {
+        return Err(ConversionError::ConflictingValuesForKey(
+            "Yaml".into(),
+            yaml_paths,
+        ));
+    }
+
     Ok(())
 }
 
@@ -35,23 +175,113 @@ fn get_base_podman_command(unit: &SystemdUnitFile, section: &str) -> PodmanComma
         &mut podman,
     );
 
+    // opt-in, so podman's own logs can be consulted when debugging a unit
+    if unit
+        .lookup_bool(QUADLET_SECTION, "PodmanDebug")
+        .unwrap_or(false)
+    {
+        podman.add("--log-level=debug");
+    }
+
     podman.extend(unit.lookup_all_args(section, "GlobalArgs"));
 
     podman
 }
 
+// Convert a quadlet artifact file (unit file with an Artifact group) to a systemd
+// service file (unit file with Service group) based on the options in the Artifact group.
+// The original Artifact group is kept around as X-Artifact.
+pub(crate) fn from_artifact_unit(
+    artifact: &SystemdUnitFile,
+    units_info_map: &mut UnitsInfoMap,
+    is_user: bool,
+) -> Result<SystemdUnitFile, ConversionError> {
+    validate(artifact, QuadletType::Artifact)?;
+
+    let mut service = SystemdUnitFile::new();
+    service.merge_from(artifact);
+
+    handle_unit_dependencies(&mut service, units_info_map)?;
+
+    let unit_info = units_info_map
+        .0
+        .get_mut(artifact.file_name())
+        .ok_or_else(|| {
+            ConversionError::InternalQuadletError("artifact".into(), artifact.path().into())
+        })?;
+    service.path = unit_info.get_service_file_name().into();
+
+    handle_default_dependencies(&mut service, is_user);
+
+    if !artifact.path().as_os_str().is_empty() {
+        service.add(UNIT_SECTION, "SourcePath", artifact.path().to_str());
+    }
+
+    let artifact_name = artifact
+        .lookup_last(ARTIFACT_SECTION, "Artifact")
+        .unwrap_or_default();
+
+    // Rename old Artifact section to X-Artifact so that systemd ignores it
+    service.rename_section(ARTIFACT_SECTION, X_ARTIFACT_SECTION);
+
+    // Rename common Quadlet section
+    service.rename_section(QUADLET_SECTION, X_QUADLET_SECTION);
+
+    // Need the containers filesystem mounted to start podman
+    service.add(UNIT_SECTION, "RequiresMountsFor", "%t/containers");
+
+    let mut podman = get_base_podman_command(artifact, ARTIFACT_SECTION);
+    podman.add("artifact");
+    podman.add("pull");
+
+    let string_keys = [
+        ("AuthFile", "--authfile"),
+        ("CertDir", "--cert-dir"),
+        ("Creds", "--creds"),
+        ("DecryptionKey", "--decryption-key"),
+        ("Retry", "--retry"),
+        ("RetryDelay", "--retry-delay"),
+    ];
+    lookup_and_add_string(artifact, ARTIFACT_SECTION, &string_keys, &mut podman);
+
+    let bool_keys = [("Quiet", "--quiet"), ("TLSVerify", "--tls-verify")];
+    lookup_and_add_bool(artifact, ARTIFACT_SECTION, &bool_keys, &mut podman);
+
+    handle_podman_args(artifact, ARTIFACT_SECTION, &mut podman);
+
+    podman.add(artifact_name.clone());
+
+    service.add_raw(
+        SERVICE_SECTION,
+        "ExecStart",
+        podman.to_escaped_string().as_str(),
+    )?;
+
+    handle_one_shot_service_section(&mut service, true);
+
+    // Store the name of the created resource
+    unit_info.resource_name = artifact_name;
+
+    Ok(service)
+}
+
 pub(crate) fn from_build_unit(
     build: &SystemdUnitFile,
     units_info_map: &mut UnitsInfoMap,
     is_user: bool,
 ) -> Result<SystemdUnitFile, ConversionError> {
+    validate(build, QuadletType::Build)?;
+
     let unit_info = units_info_map.0.get(build.file_name()).ok_or_else(|| {
         ConversionError::InternalQuadletError("build".to_string(), build.file_name().into())
     })?;
 
     // fail fast if resource name is not set
     if unit_info.resource_name.is_empty() {
-        return Err(ConversionError::NoImageTagKeySpecified);
+        return Err(ConversionError::EmptyRequiredKey(
+            "ImageTag".into(),
+            build.file_name().into(),
+        ));
     }
 
     let mut service = SystemdUnitFile::new();
@@ -59,6 +289,8 @@ pub(crate) fn from_build_unit(
     service.merge_from(build);
     service.path = unit_info.get_service_file_name().into();
 
+    handle_unit_dependencies(&mut service, units_info_map)?;
+
     handle_default_dependencies(&mut service, is_user);
 
     // Need the containers filesystem mounted to start podman
@@ -68,9 +300,6 @@ pub(crate) fn from_build_unit(
         service.add(UNIT_SECTION, "SourcePath", build.path().to_str());
     }
 
-    check_for_unknown_keys(build, BUILD_SECTION, &SUPPORTED_BUILD_KEYS)?;
-    check_for_unknown_keys(build, QUADLET_SECTION, &SUPPORTED_QUADLET_KEYS)?;
-
     // Rename old Build section to X-Build so that systemd ignores it
     service.rename_section(BUILD_SECTION, X_BUILD_SECTION);
 
@@ -80,13 +309,7 @@ pub(crate) fn from_build_unit(
     let mut podman = get_base_podman_command(build, BUILD_SECTION);
     podman.add("build");
 
-    // The `--pull` flag has to be handled separately and the `=` sign must be present
-    // see https://github.com/containers/podman/issues/24599
-    if let Some(pull) = build.lookup(BUILD_SECTION, "Pull") {
-        if !pull.is_empty() {
-            podman.add(format!("--pull={pull}"));
-        }
-    }
+    handle_pull_policy(build, BUILD_SECTION, "Pull", "--pull", &mut podman)?;
 
     let string_keys = [
         ("Arch", "--arch"),
@@ -102,12 +325,14 @@ pub(crate) fn from_build_unit(
     ];
     lookup_and_add_bool(build, BUILD_SECTION, &bool_keys, &mut podman);
 
+    check_dns_servers(build, BUILD_SECTION)?;
     let all_string_keys = [
         ("DNS", "--dns"),
         ("DNSOption", "--dns-option"),
         ("DNSSearch", "--dns-search"),
         ("GroupAdd", "--group-add"),
         ("ImageTag", "--tag"),
+        ("Tag", "--tag"),
     ];
     lookup_and_add_all_strings(build, BUILD_SECTION, &all_string_keys, &mut podman);
 
@@ -128,9 +353,12 @@ pub(crate) fn from_build_unit(
         &mut podman,
     )?;
 
+    let secrets = build.lookup_all_args(BUILD_SECTION, "Secret");
+    for secret in &secrets {
+        check_secret_options(secret, &SUPPORTED_BUILD_SECRET_OPTIONS)?;
+    }
     podman.extend(
-        build
-            .lookup_all_args(BUILD_SECTION, "Secret")
+        secrets
             .iter()
             .flat_map(|secret| ["--secret", secret])
             .map(str::to_string),
@@ -196,7 +424,7 @@ pub(crate) fn from_build_unit(
 
     handle_one_shot_service_section(&mut service, false);
 
-    return Ok(service);
+    Ok(service)
 }
 
 // Convert a quadlet container file (unit file with a Container group) to a systemd
@@ -207,6 +435,16 @@ pub(crate) fn from_container_unit(
     units_info_map: &mut UnitsInfoMap,
     is_user: bool,
 ) -> Result<SystemdUnitFile, ConversionError> {
+    // checked before anything else, e.g. handle_image_source() has side effects
+    validate(container, QuadletType::Container)?;
+
+    let image = container
+        .lookup_last(CONTAINER_SECTION, "Image")
+        .map_or(String::new(), |s| s.to_string());
+    let rootfs = container
+        .lookup_last(CONTAINER_SECTION, "Rootfs")
+        .map_or(String::new(), |s| s.to_string());
+
     let mut service = SystemdUnitFile::new();
     service.merge_from(container);
 
@@ -219,59 +457,65 @@ pub(crate) fn from_container_unit(
         service.path = unit_info.get_service_file_name().into();
     }
 
+    handle_unit_dependencies(&mut service, units_info_map)?;
+
     handle_default_dependencies(&mut service, is_user);
 
     if !container.path().as_os_str().is_empty() {
         service.add(UNIT_SECTION, "SourcePath", container.path().to_str());
     }
 
-    check_for_unknown_keys(container, CONTAINER_SECTION, &SUPPORTED_CONTAINER_KEYS)?;
-    check_for_unknown_keys(container, QUADLET_SECTION, &SUPPORTED_QUADLET_KEYS)?;
-
     // Rename old Container section to X-Container so that systemd ignores it
     service.rename_section(CONTAINER_SECTION, X_CONTAINER_SECTION);
 
     // Rename common Quadlet section
     service.rename_section(QUADLET_SECTION, X_QUADLET_SECTION);
 
-    // One image or rootfs must be specified for the container
-    let image = container
-        .lookup_last(CONTAINER_SECTION, "Image")
-        .map_or(String::new(), |s| s.to_string());
-    let rootfs = container
-        .lookup_last(CONTAINER_SECTION, "Rootfs")
-        .map_or(String::new(), |s| s.to_string());
-    if image.is_empty() && rootfs.is_empty() {
-        return Err(ConversionError::InvalidImageOrRootfs(
-            "no Image or Rootfs key specified".into(),
-        ));
-    }
-    if !image.is_empty() && !rootfs.is_empty() {
-        return Err(ConversionError::InvalidImageOrRootfs(
-            "the Image And Rootfs keys conflict can not be specified together".into(),
-        ));
-    }
-
     let image = if !image.is_empty() {
+        // the image is provided by another unit, so there's nothing to pull
+        let is_quadlet_image = matches!(
+            quadlet_type_of(&image),
+            Some(QuadletType::Build | QuadletType::Image)
+        );
+        if is_quadlet_image
+            && container
+                .lookup(CONTAINER_SECTION, "Pull")
+                .is_some_and(|pull| !pull.is_empty())
+        {
+            warn!(
+                "Pull= in {:?} has no effect, because Image={image:?} references a Quadlet unit",
+                container.path()
+            );
+        }
         handle_image_source(&image, &mut service, units_info_map)?.to_string()
     } else {
         image
     };
+    // Rootfs= may point at a .build or .image unit, just like Image=
+    let rootfs = if matches!(
+        quadlet_type_of(&rootfs),
+        Some(QuadletType::Build | QuadletType::Image)
+    ) {
+        handle_image_source(&rootfs, &mut service, units_info_map)?.to_string()
+    } else {
+        if !rootfs.is_empty() {
+            check_rootfs_options(&rootfs)?;
+        }
+        rootfs
+    };
 
     let podman_container_name = get_container_name(container);
 
     // Set PODMAN_SYSTEMD_UNIT so that podman auto-update can restart the service.
     service.add(SERVICE_SECTION, "Environment", "PODMAN_SYSTEMD_UNIT=%n");
 
-    // Only allow mixed or control-group, as nothing else works well
-    let kill_mode = service.lookup_last(SERVICE_SECTION, "KillMode");
-    match kill_mode.as_deref() {
-        None | Some("mixed") | Some("control-group") => {
-            // We default to mixed instead of control-group, because it lets conmon do its thing
-            service.set(SERVICE_SECTION, "KillMode", "mixed");
-        }
-        Some(kill_mode) => {
-            return Err(ConversionError::InvalidKillMode(kill_mode.into()));
+    handle_kill_mode(&mut service)?;
+
+    // podman waits up to StopTimeout= for the container to stop before killing it, so systemd
+    // should wait (at least) as long before it gives up on stopping the service
+    if let Some(stop_timeout) = container.lookup(CONTAINER_SECTION, "StopTimeout") {
+        if !stop_timeout.is_empty() && !service.has_key(SERVICE_SECTION, "TimeoutStopSec") {
+            service.set(SERVICE_SECTION, "TimeoutStopSec", &stop_timeout);
         }
     }
 
@@ -301,6 +545,8 @@ pub(crate) fn from_container_unit(
         service_stop_cmd.to_escaped_string().as_str(),
     )?;
 
+    handle_exec_reload(container, CONTAINER_SECTION, &mut service)?;
+
     let mut podman = get_base_podman_command(container, CONTAINER_SECTION);
 
     podman.add("run");
@@ -336,6 +582,16 @@ pub(crate) fn from_container_unit(
     podman.add("--cgroups");
     podman.add(cgroups_mode);
 
+    // podman interprets a relative WorkingDir= inside the image, which is probably not intended
+    if let Some(working_dir) = container.lookup(CONTAINER_SECTION, "WorkingDir") {
+        if !working_dir.is_empty() && !Path::new(&working_dir).is_absolute() {
+            warn!(
+                "WorkingDir={working_dir:?} in {:?} is a relative path, it will be resolved inside the container image",
+                container.path()
+            );
+        }
+    }
+
     let string_keys = [
         ("Timezone", "--tz"),
         ("PidsLimit", "--pids-limit"),
@@ -351,6 +607,11 @@ pub(crate) fn from_container_unit(
     ];
     lookup_and_add_string(container, CONTAINER_SECTION, &string_keys, &mut podman);
 
+    check_pull_policy(container, CONTAINER_SECTION, "Pull")?;
+    check_dns_servers(container, CONTAINER_SECTION)?;
+    check_add_hosts(container, CONTAINER_SECTION)?;
+    check_ulimits(container, CONTAINER_SECTION)?;
+    check_timezone(container, CONTAINER_SECTION)?;
     let all_string_keys = [
         ("NetworkAlias", "--network-alias"),
         ("Ulimit", "--ulimit"),
@@ -378,32 +639,64 @@ pub(crate) fn from_container_unit(
         &mut podman,
     )?;
 
+    // SdNotify= allows setting the mode explicitly, overriding Notify=
+    // (checked for all service types, even those it has no effect for)
+    let sd_notify = container.lookup(CONTAINER_SECTION, "SdNotify");
+    if let Some(sd_notify) = &sd_notify {
+        if !SUPPORTED_SD_NOTIFY_MODES.contains(&sd_notify.as_str()) {
+            return Err(ConversionError::UnsupportedValueForKey(
+                "SdNotify".into(),
+                sd_notify.clone(),
+            ));
+        }
+    }
+
     let service_type = container.lookup_last(SERVICE_SECTION, "Type");
     match service_type.as_deref() {
         Some("oneshot") => {}
+        // podman stays in the foreground (i.e. no `-d`) and no sd-notify is used, so systemd will
+        // consider the service started as soon as podman was (successfully) executed, i.e. before
+        // the container is actually up. Notify= and SdNotify= have no effect.
+        Some("exec" | "simple") => {}
         Some("notify") | None => {
             // If we're not in oneshot mode always use some form of sd-notify, normally via conmon,
             // but we also allow passing it to the container by setting Notify=yes
-            let notify = container.lookup(CONTAINER_SECTION, "Notify");
-            match notify {
-                Some(notify) if notify == "healthy" => podman.add("--sdnotify=healthy"),
-                _ => {
-                    let notify = container
-                        .lookup_bool(CONTAINER_SECTION, "Notify")
-                        .unwrap_or(false);
-
-                    if notify {
-                        podman.add("--sdnotify=container");
-                    } else {
-                        podman.add("--sdnotify=conmon");
-                    }
+            let sd_notify = if let Some(sd_notify) = sd_notify {
+                sd_notify
+            } else {
+                let notify = container.lookup(CONTAINER_SECTION, "Notify");
+                match notify.as_deref() {
+                    None | Some("") => "conmon",
+                    Some("healthy") => "healthy",
+                    Some(notify) => match parse_bool(notify) {
+                        Ok(true) => "container",
+                        Ok(false) => "conmon",
+                        Err(_) => {
+                            return Err(ConversionError::UnsupportedValueForKey(
+                                "Notify".into(),
+                                notify.into(),
+                            ))
+                        }
+                    },
                 }
-            }
-            service.set(SERVICE_SECTION, "Type", "notify");
-            service.set(SERVICE_SECTION, "NotifyAccess", "all");
+                .to_string()
+            };
+            podman.add(format!("--sdnotify={sd_notify}"));
+
+            if sd_notify == "ignore" {
+                // nothing would send READY=1, so systemd would wait for it until it times out
+                if service_type.is_some() {
+                    return Err(ConversionError::InvalidServiceTypeForSdNotify(sd_notify));
+                }
+                // like with Type=exec podman stays in the foreground
+                service.set(SERVICE_SECTION, "Type", "exec");
+            } else {
+                service.set(SERVICE_SECTION, "Type", "notify");
+                service.set(SERVICE_SECTION, "NotifyAccess", "all");
 
-            // Detach from container, we don't need the podman process to hang around
-            podman.add("-d");
+                // Detach from container, we don't need the podman process to hang around
+                podman.add("-d");
+            }
         }
         Some(service_type) => {
             return Err(ConversionError::InvalidServiceType(service_type.into()));
@@ -418,16 +711,14 @@ pub(crate) fn from_container_unit(
     }
 
     // Default to no higher level privileges or caps
-    let no_new_privileges = container
-        .lookup_bool(CONTAINER_SECTION, "NoNewPrivileges")
-        .unwrap_or(false);
+    let no_new_privileges =
+        lookup_bool_strict(container, CONTAINER_SECTION, "NoNewPrivileges")?.unwrap_or(false);
     if no_new_privileges {
         podman.add("--security-opt=no-new-privileges");
     }
 
-    let security_label_disable = container
-        .lookup_bool(CONTAINER_SECTION, "SecurityLabelDisable")
-        .unwrap_or(false);
+    let security_label_disable =
+        lookup_bool_strict(container, CONTAINER_SECTION, "SecurityLabelDisable")?.unwrap_or(false);
     if security_label_disable {
         podman.add_slice(&["--security-opt", "label=disable"]);
     }
@@ -500,7 +791,7 @@ pub(crate) fn from_container_unit(
         podman.add(sysctl);
     }
 
-    let read_only = container.lookup_bool(CONTAINER_SECTION, "ReadOnly");
+    let read_only = lookup_bool_strict(container, CONTAINER_SECTION, "ReadOnly")?;
     if let Some(read_only) = read_only {
         podman.add_bool("--read-only", read_only);
     }
@@ -518,7 +809,7 @@ pub(crate) fn from_container_unit(
     handle_user_mappings(container, CONTAINER_SECTION, &mut podman, true)?;
 
     handle_volumes(
-        &container,
+        container,
         CONTAINER_SECTION,
         &mut service,
         units_info_map,
@@ -527,9 +818,8 @@ pub(crate) fn from_container_unit(
 
     if let Some(update) = container.lookup(CONTAINER_SECTION, "AutoUpdate") {
         if !update.is_empty() {
-            let mut labels: HashMap<String, String> = HashMap::new();
-            labels.insert(AUTO_UPDATE_LABEL.to_string(), update.to_string());
-            podman.add_labels(&labels);
+            check_auto_update_policy(&update)?;
+            podman.add_labels(&[(AUTO_UPDATE_LABEL.to_string(), update.to_string())]);
         }
     }
 
@@ -570,13 +860,20 @@ pub(crate) fn from_container_unit(
         .map(|s| PathBuf::from(s).absolute_from_unit(container))
         .collect();
     for env_file in env_files {
+        // this happens when the unit file's own path is relative
+        if !env_file.is_absolute() && !env_file.starts_with_systemd_specifier() {
+            return Err(ConversionError::InvalidRelativeEnvironmentFile(env_file));
+        }
         podman.add("--env-file");
         podman.add(env_file.to_str());
     }
 
+    let secrets = container.lookup_all_args(CONTAINER_SECTION, "Secret");
+    for secret in &secrets {
+        check_secret_options(secret, &SUPPORTED_SECRET_OPTIONS)?;
+    }
     podman.extend(
-        container
-            .lookup_all_args(CONTAINER_SECTION, "Secret")
+        secrets
             .iter()
             .flat_map(|secret| ["--secret", secret])
             .map(str::to_string),
@@ -599,6 +896,8 @@ pub(crate) fn from_container_unit(
         &mut podman,
     )?;
 
+    // PodmanArgs= are added after all other options (incl. EnvironmentFile= and Environment=), so
+    // with podman's "last one wins" semantics they take precedence, e.g. `--env FOO=bar`
     handle_podman_args(container, CONTAINER_SECTION, &mut podman);
 
     if !image.is_empty() {
@@ -628,12 +927,16 @@ pub(crate) fn from_image_unit(
     units_info_map: &mut UnitsInfoMap,
     is_user: bool,
 ) -> Result<SystemdUnitFile, ConversionError> {
-    let unit_info = units_info_map.0.get_mut(image.file_name()).ok_or_else(|| {
-        ConversionError::InternalQuadletError("image".into(), image.path().into())
-    })?;
+    validate(image, QuadletType::Image)?;
 
     let mut service = SystemdUnitFile::new();
     service.merge_from(image);
+
+    handle_unit_dependencies(&mut service, units_info_map)?;
+
+    let unit_info = units_info_map.0.get_mut(image.file_name()).ok_or_else(|| {
+        ConversionError::InternalQuadletError("image".into(), image.path().into())
+    })?;
     service.path = unit_info.get_service_file_name().into();
 
     handle_default_dependencies(&mut service, is_user);
@@ -642,17 +945,9 @@ pub(crate) fn from_image_unit(
         service.add(UNIT_SECTION, "SourcePath", image.path().to_str());
     }
 
-    check_for_unknown_keys(image, IMAGE_SECTION, &SUPPORTED_IMAGE_KEYS)?;
-    check_for_unknown_keys(image, QUADLET_SECTION, &SUPPORTED_QUADLET_KEYS)?;
-
     let image_name = image
         .lookup_last(IMAGE_SECTION, "Image")
         .unwrap_or_default();
-    if image_name.is_empty() {
-        return Err(ConversionError::InvalidImageOrRootfs(
-            "no Image key specified".into(),
-        ));
-    }
 
     // Rename old Image section to X-Image so that systemd ignores it
     service.rename_section(IMAGE_SECTION, X_IMAGE_SECTION);
@@ -667,6 +962,8 @@ pub(crate) fn from_image_unit(
     podman.add("image");
     podman.add("pull");
 
+    handle_pull_policy(image, IMAGE_SECTION, "Policy", "--policy", &mut podman)?;
+
     let string_keys = [
         ("Arch", "--arch"),
         ("AuthFile", "--authfile"),
@@ -717,6 +1014,8 @@ pub(crate) fn from_kube_unit(
     units_info_map: &mut UnitsInfoMap,
     is_user: bool,
 ) -> Result<SystemdUnitFile, ConversionError> {
+    validate(kube, QuadletType::Kube)?;
+
     let unit_info = units_info_map
         .0
         .get(kube.file_name())
@@ -726,39 +1025,25 @@ pub(crate) fn from_kube_unit(
     service.merge_from(kube);
     service.path = unit_info.get_service_file_name().into();
 
+    handle_unit_dependencies(&mut service, units_info_map)?;
+
     handle_default_dependencies(&mut service, is_user);
 
     if !kube.path().as_os_str().is_empty() {
         service.add(UNIT_SECTION, "SourcePath", kube.path().to_str());
     }
 
-    check_for_unknown_keys(kube, KUBE_SECTION, &SUPPORTED_KUBE_KEYS)?;
-    check_for_unknown_keys(kube, QUADLET_SECTION, &SUPPORTED_QUADLET_KEYS)?;
-
     // Rename old Kube section to X-Kube so that systemd ignores it
     service.rename_section(KUBE_SECTION, X_KUBE_SECTION);
 
     // Rename common Quadlet section
     service.rename_section(QUADLET_SECTION, X_QUADLET_SECTION);
 
-    let yaml_path = kube.lookup_last(KUBE_SECTION, "Yaml").unwrap_or_default();
-    if yaml_path.is_empty() {
-        return Err(ConversionError::NoYamlKeySpecified);
-    }
+    let yaml_path = get_kube_yaml_path(kube)?;
 
     let yaml_path = PathBuf::from(yaml_path).absolute_from_unit(kube);
 
-    // Only allow mixed or control-group, as nothing else works well
-    let kill_mode = kube.lookup_last(KUBE_SECTION, "KillMode");
-    match kill_mode.as_deref() {
-        None | Some("mixed") | Some("control-group") => {
-            // We default to mixed instead of control-group, because it lets conmon do its thing
-            service.set(SERVICE_SECTION, "KillMode", "mixed");
-        }
-        Some(kill_mode) => {
-            return Err(ConversionError::InvalidKillMode(kill_mode.into()));
-        }
-    }
+    handle_kill_mode(&mut service)?;
 
     // Set PODMAN_SYSTEMD_UNIT so that podman auto-update can restart the service.
     service.add(SERVICE_SECTION, "Environment", "PODMAN_SYSTEMD_UNIT=%n");
@@ -779,7 +1064,7 @@ pub(crate) fn from_kube_unit(
         }
         Some(service_type) => {
             if service_type != "notify" && service_type != "oneshot" {
-                return Err(ConversionError::InvalidServiceType(service_type.into()));
+                return Err(ConversionError::InvalidServiceType(service_type));
             }
         }
     }
@@ -801,6 +1086,12 @@ pub(crate) fn from_kube_unit(
 
     if let Some(ecp) = kube.lookup(KUBE_SECTION, "ExitCodePropagation") {
         if !ecp.is_empty() {
+            if !SUPPORTED_EXIT_CODE_PROPAGATIONS.contains(&ecp.as_str()) {
+                return Err(ConversionError::UnsupportedValueForKey(
+                    "ExitCodePropagation".into(),
+                    ecp,
+                ));
+            }
             podman_start.add(format!("--service-exit-code-propagation={ecp}"));
         }
     }
@@ -828,6 +1119,7 @@ pub(crate) fn from_kube_unit(
             annotation_suffix = "".to_string();
             update_type = &update;
         }
+        check_auto_update_policy(update_type)?;
         podman_start.add("--annotation");
         podman_start.add(format!(
             "{AUTO_UPDATE_LABEL}{annotation_suffix}={update_type}"
@@ -841,6 +1133,13 @@ pub(crate) fn from_kube_unit(
         .collect();
     for config_map in config_maps {
         let config_map_path = config_map.absolute_from_unit(kube);
+        // not fatal, the file may still be created before the service is started
+        if !config_map_path.starts_with_systemd_specifier() && !config_map_path.exists() {
+            warn!(
+                "ConfigMap {config_map_path:?} in {:?} does not exist (yet)",
+                kube.path()
+            );
+        }
         podman_start.add("--configmap");
         podman_start.add(config_map_path.to_str());
     }
@@ -889,15 +1188,19 @@ pub(crate) fn from_network_unit(
     units_info_map: &mut UnitsInfoMap,
     is_user: bool,
 ) -> Result<SystemdUnitFile, ConversionError> {
+    validate(network, QuadletType::Network)?;
+
+    let mut service = SystemdUnitFile::new();
+    service.merge_from(network);
+
+    handle_unit_dependencies(&mut service, units_info_map)?;
+
     let unit_info = units_info_map
         .0
         .get_mut(network.file_name())
         .ok_or_else(|| {
             ConversionError::InternalQuadletError("network".into(), network.path().into())
         })?;
-
-    let mut service = SystemdUnitFile::new();
-    service.merge_from(network);
     service.path = unit_info.get_service_file_name().into();
 
     handle_default_dependencies(&mut service, is_user);
@@ -906,9 +1209,6 @@ pub(crate) fn from_network_unit(
         service.add(UNIT_SECTION, "SourcePath", network.path().to_str());
     }
 
-    check_for_unknown_keys(network, NETWORK_SECTION, &SUPPORTED_NETWORK_KEYS)?;
-    check_for_unknown_keys(network, QUADLET_SECTION, &SUPPORTED_QUADLET_KEYS)?;
-
     // Rename old Network section to X-Network so that systemd ignores it
     service.rename_section(NETWORK_SECTION, X_NETWORK_SECTION);
 
@@ -920,14 +1220,9 @@ pub(crate) fn from_network_unit(
         .lookup(NETWORK_SECTION, "NetworkName")
         .unwrap_or_default();
     let podman_network_name = if podman_network_name.is_empty() {
-        quad_replace_extension(network.path(), "", "systemd-", "")
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string()
+        derive_resource_name(network)?
     } else {
-        podman_network_name.to_string()
+        expand_resource_name_specifiers(network, &podman_network_name)
     };
 
     // Need the containers filesystem mounted to start podman
@@ -951,6 +1246,7 @@ pub(crate) fn from_network_unit(
     ];
     lookup_and_add_string(network, NETWORK_SECTION, &string_keys, &mut podman);
 
+    check_dns_servers(network, NETWORK_SECTION)?;
     lookup_and_add_all_strings(network, NETWORK_SECTION, &[("DNS", "--dns")], &mut podman);
 
     let subnets = network.lookup_all(NETWORK_SECTION, "Subnet");
@@ -1016,6 +1312,8 @@ pub(crate) fn from_pod_unit(
     units_info_map: &mut UnitsInfoMap,
     is_user: bool,
 ) -> Result<SystemdUnitFile, ConversionError> {
+    validate(pod, QuadletType::Pod)?;
+
     let unit_info = units_info_map
         .0
         .get(pod.file_name())
@@ -1025,15 +1323,14 @@ pub(crate) fn from_pod_unit(
     service.merge_from(pod);
     service.path = unit_info.get_service_file_name().into();
 
+    handle_unit_dependencies(&mut service, units_info_map)?;
+
     handle_default_dependencies(&mut service, is_user);
 
     if !pod.path().as_os_str().is_empty() {
         service.add(UNIT_SECTION, "SourcePath", pod.path().to_str());
     }
 
-    check_for_unknown_keys(pod, POD_SECTION, &SUPPORTED_POD_KEYS)?;
-    check_for_unknown_keys(pod, QUADLET_SECTION, &SUPPORTED_QUADLET_KEYS)?;
-
     // Derive pod name from unit name (with added prefix), or use user-provided name.
     let podman_pod_name = pod.lookup(POD_SECTION, "PodName").unwrap_or_default();
     let podman_pod_name = if podman_pod_name.is_empty() {
@@ -1128,8 +1425,10 @@ pub(crate) fn from_pod_unit(
         ("IP6", "--ip6"),
     ];
     // NOTE: Go Quadlet uses `lookup_and_add_all_strings()` here
-    lookup_and_add_string(&pod, POD_SECTION, &string_keys, &mut podman_start_pre);
+    lookup_and_add_string(pod, POD_SECTION, &string_keys, &mut podman_start_pre);
 
+    check_dns_servers(pod, POD_SECTION)?;
+    check_add_hosts(pod, POD_SECTION)?;
     let all_string_keys = [
         ("NetworkAlias", "--network-alias"),
         ("DNS", "--dns"),
@@ -1137,7 +1436,7 @@ pub(crate) fn from_pod_unit(
         ("DNSSearch", "--dns-search"),
         ("AddHost", "--add-host"),
     ];
-    lookup_and_add_all_strings(&pod, POD_SECTION, &all_string_keys, &mut podman_start_pre);
+    lookup_and_add_all_strings(pod, POD_SECTION, &all_string_keys, &mut podman_start_pre);
 
     handle_volumes(
         pod,
@@ -1161,7 +1460,10 @@ pub(crate) fn from_pod_unit(
 
     service.add(SERVICE_SECTION, "Environment", "PODMAN_SYSTEMD_UNIT=%n");
     service.add(SERVICE_SECTION, "Type", "forking");
-    service.add(SERVICE_SECTION, "Restart", "on-failure");
+    // Only restart on failure by default, but respect the user's choice
+    if !pod.has_key(SERVICE_SECTION, "Restart") {
+        service.add(SERVICE_SECTION, "Restart", "on-failure");
+    }
     service.add(SERVICE_SECTION, "PIDFile", "%t/%N.pid");
 
     Ok(service)
@@ -1178,15 +1480,19 @@ pub(crate) fn from_volume_unit(
     units_info_map: &mut UnitsInfoMap,
     is_user: bool,
 ) -> Result<SystemdUnitFile, ConversionError> {
+    validate(volume, QuadletType::Volume)?;
+
+    let mut service = SystemdUnitFile::new();
+    service.merge_from(volume);
+
+    handle_unit_dependencies(&mut service, units_info_map)?;
+
     let unit_info = units_info_map
         .0
         .get_mut(volume.file_name())
         .ok_or_else(|| {
             ConversionError::InternalQuadletError("volume".into(), volume.path().into())
         })?;
-
-    let mut service = SystemdUnitFile::new();
-    service.merge_from(volume);
     service.path = unit_info.get_service_file_name().into();
 
     handle_default_dependencies(&mut service, is_user);
@@ -1195,9 +1501,6 @@ pub(crate) fn from_volume_unit(
         service.add(UNIT_SECTION, "SourcePath", volume.path().to_str());
     }
 
-    check_for_unknown_keys(volume, VOLUME_SECTION, &SUPPORTED_VOLUME_KEYS)?;
-    check_for_unknown_keys(volume, QUADLET_SECTION, &SUPPORTED_QUADLET_KEYS)?;
-
     // Rename old Volume section to X-Volume so that systemd ignores it
     service.rename_section(VOLUME_SECTION, X_VOLUME_SECTION);
 
@@ -1209,14 +1512,9 @@ pub(crate) fn from_volume_unit(
         .lookup(VOLUME_SECTION, "VolumeName")
         .unwrap_or_default();
     let podman_volume_name = if podman_volume_name.is_empty() {
-        quad_replace_extension(volume.path(), "", "systemd-", "")
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string()
+        derive_resource_name(volume)?
     } else {
-        podman_volume_name.to_string()
+        expand_resource_name_specifiers(volume, &podman_volume_name)
     };
     // Store the name of the created resource
     unit_info.resource_name = podman_volume_name.clone();
@@ -1244,7 +1542,7 @@ pub(crate) fn from_volume_unit(
             )
         })?;
 
-        let image_name = handle_image_source(image_name.as_str(), &mut service, &units_info_map)?;
+        let image_name = handle_image_source(image_name.as_str(), &mut service, units_info_map)?;
 
         podman.add("--opt");
         podman.add(format!("image={image_name}"));
@@ -1298,7 +1596,7 @@ pub(crate) fn from_volume_unit(
         if let Some(mount_opts) = volume.lookup_last(VOLUME_SECTION, "Options") {
             if !mount_opts.is_empty() {
                 if dev_valid {
-                    opts.push(mount_opts.into());
+                    opts.push(mount_opts);
                 } else {
                     return Err(ConversionError::InvalidDeviceOptions);
                 }
@@ -1349,6 +1647,32 @@ fn handle_default_dependencies(service: &mut SystemdUnitFile, is_user: bool) {
     }
 }
 
+fn handle_exec_reload(
+    quadlet_unit_file: &SystemdUnitFile,
+    section: &str,
+    service_unit_file: &mut SystemdUnitFile,
+) -> Result<(), ConversionError> {
+    let reload_cmd: Vec<String> = quadlet_unit_file
+        .lookup_last_value(section, "ReloadCmd")
+        .map(|v| SplitWord::new(v.raw()).collect())
+        .unwrap_or_default();
+    if reload_cmd.is_empty() {
+        return Ok(());
+    }
+
+    // run the command inside the container started by ExecStart=
+    let mut podman = get_base_podman_command(quadlet_unit_file, section);
+    podman.add_slice(&["exec", "--cidfile=%t/%N.cid"]);
+    podman.extend(reload_cmd);
+    service_unit_file.add_raw(
+        SERVICE_SECTION,
+        "ExecReload",
+        podman.to_escaped_string().as_str(),
+    )?;
+
+    Ok(())
+}
+
 fn handle_health(unit_file: &SystemdUnit, section: &str, podman: &mut PodmanCommand) {
     let key_arg_map: [[&str; 2]; 11] = [
         ["HealthCmd", "cmd"],
@@ -1374,6 +1698,18 @@ fn handle_health(unit_file: &SystemdUnit, section: &str, podman: &mut PodmanComm
     }
 }
 
+// Quadlet units can be referenced multiple times (e.g. the same volume in `Volume=` and `Mount=`),
+// but their dependencies only need to be added once
+fn add_unit_dependency(service_unit_file: &mut SystemdUnit, key: &str, value: &str) {
+    if !service_unit_file
+        .lookup_all(UNIT_SECTION, key)
+        .iter()
+        .any(|v| v == value)
+    {
+        service_unit_file.add(UNIT_SECTION, key, value);
+    }
+}
+
 fn handle_image_source<'a>(
     quadlet_image_name: &'a str,
     service_unit_file: &mut SystemdUnitFile,
@@ -1393,15 +1729,44 @@ fn handle_image_source<'a>(
                 .to_str()
                 .expect("image service name is not a valid UTF-8 string")
                 .to_string();
-            service_unit_file.add(UNIT_SECTION, "Requires", &image_service_name);
-            service_unit_file.add(UNIT_SECTION, "After", &image_service_name);
+            add_unit_dependency(service_unit_file, "Requires", &image_service_name);
+            add_unit_dependency(service_unit_file, "After", &image_service_name);
 
             let image_name = unit_info.resource_name.as_str();
+
+            // with --all-tags the image unit doesn't pull a single image, the reference is ambiguous
+            if extension == "image"
+                && unit_info
+                    .unit_file
+                    .lookup_bool(IMAGE_SECTION, "AllTags")
+                    .unwrap_or(false)
+            {
+                warn!(
+                    "{:?} references {quadlet_image_name:?}, which pulls all tags of {image_name:?} (AllTags=true), it's ambiguous which one will be used",
+                    service_unit_file.path()
+                );
+            }
+
             return Ok(image_name);
         }
     }
 
-    return Ok(quadlet_image_name);
+    Ok(quadlet_image_name)
+}
+
+// Only allow mixed or control-group, as nothing else works well
+// With `process` or `none` systemd would only stop podman (or conmon), but not the container's
+// processes, which would keep running outside of the service's control.
+fn handle_kill_mode(service: &mut SystemdUnitFile) -> Result<(), ConversionError> {
+    match service.lookup_last(SERVICE_SECTION, "KillMode").as_deref() {
+        None => {
+            // We default to mixed instead of control-group, because it lets conmon do its thing
+            service.set(SERVICE_SECTION, "KillMode", "mixed");
+            Ok(())
+        }
+        Some("mixed" | "control-group") => Ok(()),
+        Some(kill_mode) => Err(ConversionError::InvalidKillMode(kill_mode.into())),
+    }
 }
 
 fn handle_log_driver(unit_file: &SystemdUnit, section: &str, podman: &mut PodmanCommand) {
@@ -1411,16 +1776,24 @@ fn handle_log_driver(unit_file: &SystemdUnit, section: &str, podman: &mut Podman
     }
 }
 
-fn handle_log_opt(unit_file: &SystemdUnit, section: &str, podman: &mut PodmanCommand) {
-    podman.extend(
-        unit_file
-            .lookup_all_strv(section, "LogOpt")
-            .iter()
-            .flat_map(|log_opt| ["--log-opt", log_opt])
-            .map(str::to_string),
-    )
-}
-
+fn handle_log_opt(unit_file: &SystemdUnitFile, section: &str, podman: &mut PodmanCommand) {
+    for log_opt in unit_file.lookup_all_strv(section, "LogOpt") {
+        podman.add("--log-opt");
+        match log_opt.strip_prefix("path=").map(PathBuf::from) {
+            // like EnvironmentFile= relative paths are relative to the unit file, not podman's working directory
+            Some(path)
+                if !path.as_os_str().is_empty()
+                    && path.is_relative()
+                    && !path.starts_with_systemd_specifier() =>
+            {
+                let path = path.absolute_from_unit(unit_file);
+                podman.add(format!("path={}", path.to_str()));
+            }
+            _ => podman.add(log_opt),
+        }
+    }
+}
+
 fn handle_networks(
     quadlet_unit_file: &SystemdUnit,
     section: &str,
@@ -1429,16 +1802,22 @@ fn handle_networks(
     podman: &mut PodmanCommand,
 ) -> Result<(), ConversionError> {
     for network in quadlet_unit_file.lookup_all(section, "Network") {
+        let network = network.trim();
         if !network.is_empty() {
-            let mut quadlet_network_name = network.as_str();
+            let mut quadlet_network_name = network;
             let mut options: Option<&str> = None;
             if let Some((_network_name, _options)) = network.split_once(':') {
                 quadlet_network_name = _network_name;
                 options = Some(_options);
             }
 
-            let is_network_unit = quadlet_network_name.ends_with(".network");
-            let is_container_unit = quadlet_network_name.ends_with(".container");
+            let quadlet_type = quadlet_type_of(quadlet_network_name);
+            let is_network_unit = quadlet_type == Some(QuadletType::Network);
+            let is_container_unit = quadlet_type == Some(QuadletType::Container);
+
+            if is_container_unit || ["container", "host", "none"].contains(&quadlet_network_name) {
+                check_keys_for_network_mode(quadlet_unit_file, section, network)?;
+            }
 
             if is_network_unit || is_container_unit {
                 let unit_info = units_info_map
@@ -1461,12 +1840,16 @@ fn handle_networks(
 
                 // the systemd unit name is $name-network.service
                 let service_file_name = unit_info.get_service_file_name();
-                service_unit_file.add(
-                    UNIT_SECTION,
+                add_unit_dependency(
+                    service_unit_file,
                     "Requires",
                     service_file_name.to_str().unwrap(),
                 );
-                service_unit_file.add(UNIT_SECTION, "After", service_file_name.to_str().unwrap());
+                add_unit_dependency(
+                    service_unit_file,
+                    "After",
+                    service_file_name.to_str().unwrap(),
+                );
 
                 quadlet_network_name = unit_info.resource_name.as_str();
             }
@@ -1481,7 +1864,7 @@ fn handle_networks(
                 if is_container_unit {
                     podman.add(format!("container:{quadlet_network_name}"));
                 } else {
-                    podman.add(format!("{quadlet_network_name}"));
+                    podman.add(quadlet_network_name.to_string());
                 }
             }
         }
@@ -1490,6 +1873,30 @@ fn handle_networks(
     Ok(())
 }
 
+// These network modes don't set up a network namespace for the container (e.g. host), or
+// share one with another container, so network settings for the container are not possible
+fn check_keys_for_network_mode(
+    unit_file: &SystemdUnit,
+    section: &str,
+    network: &str,
+) -> Result<(), ConversionError> {
+    for key in ["IP", "IP6", "NetworkAlias"] {
+        if !unit_file.lookup_all(section, key).is_empty() {
+            return Err(ConversionError::InvalidNetworkMode(
+                network.into(),
+                key.into(),
+            ));
+        }
+    }
+
+    // podman only discards these (with a warning)
+    if !unit_file.lookup_all(section, "PublishPort").is_empty() {
+        warn!("PublishPort= has no effect with Network={network}");
+    }
+
+    Ok(())
+}
+
 fn handle_one_shot_service_section(service: &mut SystemdUnitFile, remain_after_exit: bool) {
     // The default syslog identifier is the exec basename (podman) which isn't very useful here
     if service
@@ -1501,10 +1908,8 @@ fn handle_one_shot_service_section(service: &mut SystemdUnitFile, remain_after_e
     if service.lookup(SERVICE_SECTION, "Type").is_none() {
         service.set(SERVICE_SECTION, "Type", "oneshot")
     }
-    if remain_after_exit {
-        if service.lookup(SERVICE_SECTION, "RemainAfterExit").is_none() {
-            service.set(SERVICE_SECTION, "RemainAfterExit", "yes")
-        }
+    if remain_after_exit && service.lookup(SERVICE_SECTION, "RemainAfterExit").is_none() {
+        service.set(SERVICE_SECTION, "RemainAfterExit", "yes")
     }
 }
 
@@ -1521,14 +1926,14 @@ fn handle_pod(
 ) -> Result<(), ConversionError> {
     if let Some(pod) = quadlet_unit.lookup(section, "Pod") {
         if !pod.is_empty() {
-            if !pod.ends_with(".pod") {
+            if quadlet_type_of(&pod) != Some(QuadletType::Pod) {
                 return Err(ConversionError::InvalidPod(pod));
             }
 
             let pod_info = units_info_map
                 .0
                 .get_mut(&OsString::from(&pod))
-                .ok_or_else(|| ConversionError::PodNotFound(pod))?;
+                .ok_or(ConversionError::PodNotFound(pod))?;
             podman.add("--pod-id-file");
             podman.add(format!("%t/{}.pod-id", pod_info.service_name));
 
@@ -1559,6 +1964,26 @@ fn handle_publish_ports(unit_file: &SystemdUnit, section: &str, podman: &mut Pod
     lookup_and_add_all_strings(unit_file, section, &[("PublishPort", "--publish")], podman);
 }
 
+// Pull policy flags (e.g. `--pull`) have to be handled separately and the `=` sign must be present
+// see https://github.com/containers/podman/issues/24599
+fn handle_pull_policy(
+    quadlet_unit_file: &SystemdUnit,
+    section: &str,
+    key: &str,
+    flag: &str,
+    podman: &mut PodmanCommand,
+) -> Result<(), ConversionError> {
+    check_pull_policy(quadlet_unit_file, section, key)?;
+
+    if let Some(policy) = quadlet_unit_file.lookup(section, key) {
+        if !policy.is_empty() {
+            podman.add(format!("{flag}={policy}"));
+        }
+    }
+
+    Ok(())
+}
+
 fn handle_set_working_directory(
     quadlet_unit_file: &SystemdUnitFile,
     service_unit_file: &mut SystemdUnitFile,
@@ -1589,7 +2014,10 @@ fn handle_set_working_directory(
             if let Some(yaml) = quadlet_unit_file.lookup(quadlet_section, "Yaml") {
                 relative_to_file = PathBuf::from(yaml)
             } else {
-                return Err(ConversionError::NoYamlKeySpecified);
+                return Err(ConversionError::EmptyRequiredKey(
+                    "Yaml".into(),
+                    quadlet_unit_file.file_name().into(),
+                ));
             }
         }
         "file" => {
@@ -1669,24 +2097,34 @@ fn handle_storage_source(
             .to_string();
     }
 
+    let is_quadlet_source = match quadlet_type_of(&source) {
+        Some(QuadletType::Volume) => true,
+        Some(QuadletType::Image) => check_image,
+        _ => false,
+    };
+
     if source.starts_with('/') {
         // Absolute path
-        service_unit_file.add(UNIT_SECTION, "RequiresMountsFor", &source);
-    } else if source.ends_with(".volume") || (check_image && source.ends_with(".image")) {
+        add_unit_dependency(service_unit_file, "RequiresMountsFor", &source);
+    } else if is_quadlet_source {
         let source_unit_info = units_info_map
             .0
             .get(&OsString::from(&source))
-            .ok_or_else(|| ConversionError::SourceNotFound(source))?;
+            .ok_or(ConversionError::SourceNotFound(source))?;
 
         // the systemd unit name is $name-volume.service
         let volume_service_name = source_unit_info.get_service_file_name();
 
-        service_unit_file.add(
-            UNIT_SECTION,
+        add_unit_dependency(
+            service_unit_file,
             "Requires",
             volume_service_name.to_str().unwrap(),
         );
-        service_unit_file.add(UNIT_SECTION, "After", volume_service_name.to_str().unwrap());
+        add_unit_dependency(
+            service_unit_file,
+            "After",
+            volume_service_name.to_str().unwrap(),
+        );
 
         source = source_unit_info.resource_name.clone();
     }
@@ -1694,6 +2132,223 @@ fn handle_storage_source(
     Ok(source)
 }
 
+// The type of the Quadlet unit `name` refers to (e.g. `foo.volume`), if any
+fn quadlet_type_of(name: &str) -> Option<QuadletType> {
+    Path::new(name)
+        .extension()
+        .and_then(QuadletType::from_extension)
+}
+
+// Splits a glob pattern into the leading directories without any glob characters and the rest
+// e.g. `/data/*/foo` becomes `("/data", "*/foo")`
+fn split_glob_pattern(pattern: &str) -> (&str, &str) {
+    let Some(glob_start) = pattern.find(['*', '?', '[']) else {
+        return (pattern, "");
+    };
+
+    match pattern[..glob_start].rfind('/') {
+        Some(0) => ("/", &pattern[1..]),
+        Some(i) => (&pattern[..i], &pattern[i + 1..]),
+        None => ("", pattern),
+    }
+}
+
+// podman auto-update ignores containers with unknown policies, so typos would go unnoticed
+fn check_auto_update_policy(policy: &str) -> Result<(), ConversionError> {
+    if !SUPPORTED_AUTO_UPDATE_POLICIES.contains(&policy) {
+        return Err(ConversionError::UnsupportedValueForKey(
+            "AutoUpdate".into(),
+            policy.into(),
+        ));
+    }
+
+    if policy == "image" {
+        warn!("AutoUpdate=image is deprecated, use AutoUpdate=registry instead");
+    }
+
+    Ok(())
+}
+
+// DNS servers have to be IP addresses, `none` disables the DNS configuration of containers
+fn check_dns_servers(unit_file: &SystemdUnit, section: &str) -> Result<(), ConversionError> {
+    let all_dns = unit_file.lookup_all(section, "DNS");
+    for dns in &all_dns {
+        if dns != "none" && dns.parse::<IpAddr>().is_err() {
+            return Err(ConversionError::UnsupportedValueForKey(
+                "DNS".into(),
+                dns.clone(),
+            ));
+        }
+    }
+
+    // "none" disables DNS, so it can't be combined with actual servers
+    // (an empty `DNS=` can be used to drop servers set before, e.g. in a drop-in)
+    if all_dns.len() > 1 && all_dns.iter().any(|dns| dns == "none") {
+        return Err(ConversionError::ConflictingValuesForKey(
+            "DNS".into(),
+            all_dns,
+        ));
+    }
+
+    Ok(())
+}
+
+// Timezones are names of files in the zoneinfo database (or "local"), so they mustn't point
+// outside of it
+fn check_timezone(unit_file: &SystemdUnit, section: &str) -> Result<(), ConversionError> {
+    if let Some(timezone) = unit_file.lookup_last(section, "Timezone") {
+        if timezone.split('/').any(|component| component == "..") {
+            return Err(ConversionError::UnsupportedValueForKey(
+                "Timezone".into(),
+                timezone,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// An empty value means podman's default policy
+fn check_pull_policy(
+    unit_file: &SystemdUnit,
+    section: &str,
+    key: &str,
+) -> Result<(), ConversionError> {
+    match unit_file.lookup(section, key) {
+        Some(policy)
+            if !policy.is_empty() && !SUPPORTED_PULL_POLICIES.contains(&policy.as_str()) =>
+        {
+            Err(ConversionError::UnsupportedValueForKey(key.into(), policy))
+        }
+        _ => Ok(()),
+    }
+}
+
+// podman only notices malformed host entries when starting the container
+fn check_add_hosts(unit_file: &SystemdUnit, section: &str) -> Result<(), ConversionError> {
+    for add_host in unit_file.lookup_all(section, "AddHost") {
+        // IPv6 addresses contain colons themselves, so split at the first one
+        let is_valid = add_host.split_once(':').is_some_and(|(hostnames, ip)| {
+            let ip = ip
+                .strip_prefix('[')
+                .and_then(|ip| ip.strip_suffix(']'))
+                .unwrap_or(ip);
+            hostnames.split(';').all(|hostname| !hostname.is_empty())
+                && (ip == "host-gateway" || ip.parse::<IpAddr>().is_ok())
+        });
+        if !is_valid {
+            return Err(ConversionError::InvalidAddHost(add_host));
+        }
+    }
+
+    Ok(())
+}
+
+// Secrets are given as `name[,option=value...]` (or with the name as an option, e.g. `source=name`)
+// podman only notices typos in the options when starting the container, though
+fn check_secret_options(secret: &str, supported_options: &[&str]) -> Result<(), ConversionError> {
+    let mut tokens = secret.split(',').peekable();
+    // the name may be given without a key
+    tokens.next_if(|name| !name.contains('='));
+
+    for token in tokens {
+        let option = token.split_once('=').map_or(token, |(key, _)| key);
+        if !supported_options.contains(&option) {
+            return Err(ConversionError::InvalidSecretOption(
+                secret.into(),
+                option.into(),
+                supported_options.join(", "),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// podman only notices malformed ulimits when starting the container
+fn check_ulimits(unit_file: &SystemdUnit, section: &str) -> Result<(), ConversionError> {
+    let is_limit = |limit: &str| {
+        limit == "unlimited" || limit == "host" || limit.parse::<i64>().is_ok_and(|l| l >= -1)
+    };
+
+    for ulimit in unit_file.lookup_all(section, "Ulimit") {
+        // copies all limits from the host
+        if ulimit == "host" {
+            continue;
+        }
+
+        let is_valid = ulimit.split_once('=').is_some_and(|(name, limits)| {
+            let (soft, hard) = limits.split_once(':').unwrap_or((limits, limits));
+            SUPPORTED_ULIMIT_NAMES.contains(&name) && is_limit(soft) && is_limit(hard)
+        });
+        if !is_valid {
+            return Err(ConversionError::InvalidUlimit(ulimit));
+        }
+    }
+
+    Ok(())
+}
+
+// Rootfs paths may be followed by `:`-separated options (e.g. `/path:O` or `/path:idmap=uids=...`)
+fn check_rootfs_options(rootfs: &str) -> Result<(), ConversionError> {
+    for option in rootfs.split(':').skip(1) {
+        let (name, _) = option.split_once('=').unwrap_or((option, ""));
+        if !SUPPORTED_ROOTFS_OPTIONS.contains(&name) {
+            return Err(ConversionError::UnsupportedValueForKey(
+                "Rootfs".into(),
+                rootfs.into(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Translates dependencies on other Quadlet units (e.g. `Requires=db.volume`) in the `[Unit]`
+// section into dependencies on their generated services (e.g. `Requires=db-volume.service`).
+fn handle_unit_dependencies(
+    service: &mut SystemdUnitFile,
+    units_info_map: &UnitsInfoMap,
+) -> Result<(), ConversionError> {
+    for key in UNIT_DEPENDENCY_KEYS {
+        let deps = service.lookup_all_strv(UNIT_SECTION, key);
+        if deps.is_empty() {
+            continue;
+        }
+
+        let mut translated = false;
+        let mut translated_deps = Vec::with_capacity(deps.len());
+        for dep in deps {
+            if QuadletType::from_path(Path::new(&dep)).is_err() {
+                translated_deps.push(dep);
+                continue;
+            }
+
+            let unit_info = units_info_map
+                .0
+                .get(&OsString::from(&dep))
+                .ok_or(ConversionError::UnitDependencyNotFound(dep))?;
+            translated_deps.push(
+                unit_info
+                    .get_service_file_name()
+                    .to_str()
+                    .expect("service name is not a valid UTF-8 string")
+                    .to_string(),
+            );
+            translated = true;
+        }
+
+        if !translated {
+            continue;
+        }
+
+        service.unset(UNIT_SECTION, key);
+        service.add(UNIT_SECTION, key, translated_deps.join(" ").as_str());
+    }
+
+    Ok(())
+}
+
 fn handle_user(
     unit_file: &SystemdUnit,
     section: &str,
@@ -1702,7 +2357,7 @@ fn handle_user(
     let user = unit_file.lookup(section, "User");
     let group = unit_file.lookup(section, "Group");
 
-    return match (user, group) {
+    match (user, group) {
         // if both are "empty" we return `Ok`
         (None, None) => Ok(()),
         (None, Some(group)) if !group.is_empty() => Err(ConversionError::InvalidGroup),
@@ -1719,7 +2374,7 @@ fn handle_user(
             Ok(())
         }
         (Some(_), Some(_)) => Ok(()),
-    };
+    }
 }
 
 fn handle_user_mappings(
@@ -1739,12 +2394,14 @@ fn handle_user_mappings(
     }
 
     for uid_map in unit_file.lookup_all_strv(section, "UIDMap") {
+        check_id_mapping("UIDMap", &uid_map)?;
         podman.add("--uidmap");
         podman.add(uid_map);
         mappings_defined = true;
     }
 
     for gid_map in unit_file.lookup_all_strv(section, "GIDMap") {
+        check_id_mapping("GIDMap", &gid_map)?;
         podman.add("--gidmap");
         podman.add(gid_map);
         mappings_defined = true;
@@ -1778,7 +2435,33 @@ fn handle_user_mappings(
         return Ok(());
     }
 
-    return handle_user_remap(unit_file, section, podman, support_manual);
+    handle_user_remap(unit_file, section, podman, support_manual)
+}
+
+// Mappings have the form `[+]container_id:[@]host_id[:size]` (see podman-run(1))
+fn check_id_mapping(key: &str, mapping: &str) -> Result<(), ConversionError> {
+    let fields: Vec<&str> = mapping
+        .strip_prefix('+')
+        .unwrap_or(mapping)
+        .split(':')
+        .collect();
+    let is_valid = matches!(fields.len(), 2 | 3)
+        && fields.iter().enumerate().all(|(i, field)| {
+            let field = if i == 1 {
+                field.strip_prefix('@').unwrap_or(field)
+            } else {
+                field
+            };
+            field.parse::<u32>().is_ok()
+        });
+
+    if !is_valid {
+        return Err(ConversionError::InvalidRemapUsers(format!(
+            "invalid {key} {mapping:?}: must be of the form container_id:host_id[:size]"
+        )));
+    }
+
+    Ok(())
 }
 
 fn handle_user_remap(
@@ -1892,7 +2575,8 @@ fn handle_volumes(
     podman: &mut PodmanCommand,
 ) -> Result<(), ConversionError> {
     for volume in quadlet_unit_file.lookup_all(section, "Volume") {
-        let parts: Vec<&str> = volume.split(':').collect();
+        // options are everything after the destination, even if they contain colons themselves
+        let parts: Vec<&str> = volume.splitn(3, ':').collect();
 
         let mut source = String::new();
         let dest;
@@ -2046,7 +2730,7 @@ fn lookup_and_add_bool(
     podman: &mut PodmanCommand,
 ) {
     for (key, flag) in keys {
-        if let Some(val) = unit.lookup_bool(section, *key) {
+        if let Some(val) = unit.lookup_bool(section, key) {
             podman.add_bool(*flag, val);
         }
     }
@@ -2065,7 +2749,7 @@ fn lookup_and_add_all_strings(
     // but all the `&str`s should get "owned" in the end by `to_string()` and passed on to `podman`
     for (key, flag) in keys {
         podman.extend(
-            unit.lookup_all(section, *key)
+            unit.lookup_all(section, key)
                 .iter()
                 .flat_map(|val| [*flag, val])
                 .map(str::to_string),
@@ -2073,6 +2757,20 @@ fn lookup_and_add_all_strings(
     }
 }
 
+// For security related keys, where typos must not silently disable protections
+fn lookup_bool_strict(
+    unit: &SystemdUnit,
+    section: &str,
+    key: &str,
+) -> Result<Option<bool>, ConversionError> {
+    unit.lookup_bool_strict(section, key).map_err(|_| {
+        ConversionError::UnsupportedValueForKey(
+            key.into(),
+            unit.lookup_last(section, key).unwrap_or_default(),
+        )
+    })
+}
+
 fn lookup_and_add_string(
     unit: &SystemdUnit,
     section: &str,
@@ -2080,7 +2778,7 @@ fn lookup_and_add_string(
     podman: &mut PodmanCommand,
 ) {
     for (key, flag) in keys {
-        if let Some(val) = unit.lookup(section, *key) {
+        if let Some(val) = unit.lookup(section, key) {
             if !val.is_empty() {
                 podman.add(*flag);
                 podman.add(val);
@@ -2089,17 +2787,73 @@ fn lookup_and_add_string(
     }
 }
 
+// Replaces only the last extension (i.e. `web.v2.container` has the base name `web.v2`).
+// Paths without a file name (e.g. empty ones) are returned unchanged.
 pub(crate) fn quad_replace_extension(
     file: &Path,
     new_extension: &str,
     extra_prefix: &str,
     extra_suffix: &str,
 ) -> PathBuf {
-    let base_name = file.file_stem().unwrap().to_str().unwrap();
+    let Some(base_name) = file.file_stem() else {
+        return file.to_path_buf();
+    };
+
+    let mut file_name = OsString::from(extra_prefix);
+    file_name.push(base_name);
+    file_name.push(extra_suffix);
+    file_name.push(new_extension);
+
+    file.with_file_name(file_name)
+}
+
+// Derives the name of a Podman resource (e.g. network or volume) from the unit's file name.
+// Characters podman doesn't allow at the end of names (e.g. from `foo..network`) are trimmed.
+fn derive_resource_name(unit_file: &SystemdUnitFile) -> Result<String, ConversionError> {
+    let name = quad_replace_extension(unit_file.path(), "", "systemd-", "");
+    let name = name
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .trim_end_matches(|c: char| c.is_whitespace() || c == '.' || c == '-' || c == '_');
+
+    static RESOURCE_NAME_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new("^[a-zA-Z0-9][a-zA-Z0-9_.-]*$").expect("resource name regex should be valid")
+    });
+    if !RESOURCE_NAME_RE.is_match(name) {
+        return Err(ConversionError::InvalidResourceName(name.into()));
+    }
+
+    Ok(name.into())
+}
 
-    file.with_file_name(format!(
-        "{extra_prefix}{base_name}{extra_suffix}{new_extension}"
-    ))
+// Expands `%N` in user-provided resource names, so other units can refer to the created resource.
+// NOTE: unlike in systemd `%N` is the name of the Quadlet unit (e.g. `foo` for `foo.volume`),
+// not the one of the generated service (i.e. `foo-volume`).
+// Other specifiers are kept for systemd to expand.
+fn expand_resource_name_specifiers(unit_file: &SystemdUnitFile, name: &str) -> String {
+    let unit_name = quad_replace_extension(unit_file.path(), "", "", "");
+    let unit_name = unit_name.file_name().unwrap().to_str().unwrap();
+
+    let mut expanded = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('N') => expanded.push_str(unit_name),
+            Some(c) => {
+                expanded.push('%');
+                expanded.push(c);
+            }
+            None => expanded.push('%'),
+        }
+    }
+
+    expanded
 }
 
 fn resolve_container_mount_params(
@@ -2110,6 +2864,21 @@ fn resolve_container_mount_params(
 ) -> Result<String, ConversionError> {
     let (mount_type, tokens) = find_mount_type(mount.as_str())?;
 
+    // Some mount types (e.g. BuildKit-style cache mounts) only work with `RUN --mount` in
+    // Containerfiles and would only fail once podman run tries to use them
+    if UNSUPPORTED_RUN_MOUNT_TYPES.contains(&mount_type.as_str()) {
+        return Err(ConversionError::UnsupportedMountType(mount_type));
+    }
+
+    // podman would reject these anyway, but only when the service is started
+    if mount_type == "tmpfs"
+        && tokens
+            .iter()
+            .any(|token| token.starts_with("source=") || token.starts_with("src="))
+    {
+        return Err(ConversionError::InvalidMountSourceForType(mount_type));
+    }
+
     // Source resolution is required only for these types of mounts
     if !(mount_type == "volume"
         || mount_type == "bind"
@@ -2124,13 +2893,30 @@ fn resolve_container_mount_params(
     for token in tokens.iter() {
         if token.starts_with("source=") || token.starts_with("src=") {
             if let Some((_k, v)) = token.split_once('=') {
-                let resolved_source = handle_storage_source(
-                    container_unit_file,
-                    service_unit_file,
-                    v,
-                    units_info_map,
-                    true,
-                )?;
+                let resolved_source = if mount_type == "glob" {
+                    // systemd can only wait for the directory the pattern matches in to be mounted
+                    let (dir, pattern) = split_glob_pattern(v);
+                    let dir = handle_storage_source(
+                        container_unit_file,
+                        service_unit_file,
+                        dir,
+                        units_info_map,
+                        true,
+                    )?;
+                    if pattern.is_empty() {
+                        dir
+                    } else {
+                        PathBuf::from(dir).join(pattern).to_str().to_string()
+                    }
+                } else {
+                    handle_storage_source(
+                        container_unit_file,
+                        service_unit_file,
+                        v,
+                        units_info_map,
+                        true,
+                    )?
+                };
                 csv_writer.write_field(format!("source={resolved_source}"))?;
             } else {
                 return Err(ConversionError::InvalidMountSource);
@@ -2141,15 +2927,2737 @@ fn resolve_container_mount_params(
     }
     csv_writer.write_record(None::<&[u8]>)?;
 
-    return Ok(String::from_utf8(
+    Ok(String::from_utf8(
         csv_writer
             .into_inner()
             .expect("connot convert Mount params back into CSV"),
     )
-    .expect("connot convert Mount params back into CSV"));
+    .expect("connot convert Mount params back into CSV"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::quadlet::logger::test_logger::capture_logs;
+    use crate::quadlet::podman_command::unescape_exec;
+    use log::Level;
+
+    fn load_quadlet(path: &str, data: &str) -> (SystemdUnitFile, UnitsInfoMap) {
+        let unit_file = SystemdUnitFile::load_from_str(Path::new(path), data).unwrap();
+        let quadlet = QuadletUnitFile::from_unit_file(unit_file.clone()).unwrap();
+
+        (unit_file, UnitsInfoMap::from_quadlet_units(vec![quadlet]))
+    }
+
+    mod check_for_conflicting_yaml {
+        use super::*;
+
+        #[test]
+        fn fails_with_different_values() {
+            for (yaml, expected) in [
+                (
+                    "Yaml=/foo.yml\nYaml=/bar.yml\n",
+                    vec!["/foo.yml", "/bar.yml"],
+                ),
+                (
+                    "Yaml=/foo.yml\nYaml=/bar.yml\nYaml=/foo.yml\n",
+                    vec!["/foo.yml", "/bar.yml"],
+                ),
+            ] {
+                let kube = SystemdUnitFile::load_from_str(
+                    Path::new("foo.kube"),
+                    &format!("[Kube]\n{yaml}"),
+                )
+                .unwrap();
+
+                assert!(
+                    matches!(
+                        check_for_conflicting_yaml(&kube),
+                        Err(ConversionError::ConflictingValuesForKey(key, values)) if key == "Yaml" && values == expected
+                    ),
+                    "{yaml}"
+                );
+            }
+        }
+
+        #[test]
+        fn accepts_repeated_or_reset_values() {
+            for yaml in [
+                "",
+                "Yaml=/foo.yml\n",
+                "Yaml=/foo.yml\nYaml=/foo.yml\n",
+                "Yaml=/bar.yml\nYaml=\nYaml=/foo.yml\n",
+            ] {
+                let kube = SystemdUnitFile::load_from_str(
+                    Path::new("foo.kube"),
+                    &format!("[Kube]\n{yaml}"),
+                )
+                .unwrap();
+
+                assert!(check_for_conflicting_yaml(&kube).is_ok(), "{yaml}");
+            }
+        }
+    }
+
+    mod check_add_hosts {
+        use super::*;
+
+        #[test]
+        fn accepts_valid_hosts() {
+            for add_host in [
+                "db:10.0.0.2",
+                "api:host-gateway",
+                "db6:fd00::2",
+                "db6:[fd00::2]",
+                "db;database:10.0.0.2",
+            ] {
+                let (container, _) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=foo\nAddHost={add_host}\n"),
+                );
+
+                assert!(
+                    check_add_hosts(&container, CONTAINER_SECTION).is_ok(),
+                    "{add_host}"
+                );
+            }
+        }
+
+        #[test]
+        fn fails_with_malformed_hosts() {
+            for add_host in ["foo", "foo:", ":10.0.0.2", "db;:10.0.0.2", "db:10.0.0.256"] {
+                let (pod, _) = load_quadlet(
+                    "foo.pod",
+                    &format!("[Pod]\nAddHost=api:host-gateway\nAddHost={add_host}\n"),
+                );
+
+                assert!(
+                    matches!(
+                        check_add_hosts(&pod, POD_SECTION),
+                        Err(ConversionError::InvalidAddHost(value)) if value == add_host
+                    ),
+                    "{add_host}"
+                );
+            }
+        }
+
+        #[test]
+        fn is_checked_when_converting() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo\nAddHost=foo\n");
+
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::InvalidAddHost(value)) if value == "foo"
+            ));
+        }
+    }
+
+    mod check_auto_update_policy {
+        use super::*;
+
+        #[test]
+        fn accepts_supported_policies() {
+            for policy in ["disabled", "image", "local", "registry"] {
+                let (container, mut units_info_map) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=localhost/foo\nAutoUpdate={policy}\n"),
+                );
+
+                let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+                assert!(service
+                    .lookup_last(SERVICE_SECTION, "ExecStart")
+                    .unwrap()
+                    .contains(&format!(" --label io.containers.autoupdate={policy} ")));
+            }
+        }
+
+        #[test]
+        fn accepts_disabled_policy_of_kube_containers() {
+            let (kube, mut units_info_map) = load_quadlet(
+                "foo.kube",
+                "[Kube]\nYaml=/foo.yml\nAutoUpdate=disabled\nAutoUpdate=foo/local\n",
+            );
+
+            let service = from_kube_unit(&kube, &mut units_info_map, false).unwrap();
+            let exec_start = service.lookup_last(SERVICE_SECTION, "ExecStartPre").unwrap_or_default()
+                + &service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap();
+
+            assert!(
+                exec_start.contains("io.containers.autoupdate=disabled"),
+                "{exec_start}"
+            );
+            assert!(
+                exec_start.contains("io.containers.autoupdate/foo=local"),
+                "{exec_start}"
+            );
+        }
+
+        #[test]
+        fn warns_about_deprecated_image_policy() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=localhost/foo\nAutoUpdate=image\n",
+            );
+
+            let (service, records) =
+                capture_logs(|| from_container_unit(&container, &mut units_info_map, false));
+
+            assert!(service.is_ok());
+            assert!(records.contains(&(
+                Level::Warn,
+                "AutoUpdate=image is deprecated, use AutoUpdate=registry instead".into()
+            )));
+        }
+
+        #[test]
+        fn doesnt_warn_about_other_policies() {
+            for policy in ["disabled", "local", "registry"] {
+                let (container, mut units_info_map) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=localhost/foo\nAutoUpdate={policy}\n"),
+                );
+
+                let (_, records) =
+                    capture_logs(|| from_container_unit(&container, &mut units_info_map, false));
+
+                assert!(
+                    !records.iter().any(|(_, msg)| msg.contains("deprecated")),
+                    "{policy}: {records:?}"
+                );
+            }
+        }
+
+        #[test]
+        fn fails_with_typo_in_container() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=localhost/foo\nAutoUpdate=registy\n",
+            );
+
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "AutoUpdate" && value == "registy"
+            ));
+        }
+
+        #[test]
+        fn checks_policies_of_kube_containers() {
+            let (kube, mut units_info_map) = load_quadlet(
+                "foo.kube",
+                "[Kube]\nYaml=/foo.yml\nAutoUpdate=registry\nAutoUpdate=foo/locl\n",
+            );
+
+            assert!(matches!(
+                from_kube_unit(&kube, &mut units_info_map, false),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "AutoUpdate" && value == "locl"
+            ));
+        }
+    }
+
+    mod check_dns_servers {
+        use super::*;
+
+        #[test]
+        fn accepts_ip_addresses_and_none() {
+            for dns in ["8.8.8.8", "2001:4860:4860::8888", "none"] {
+                let (network, _) = load_quadlet("foo.network", &format!("[Network]\nDNS={dns}\n"));
+
+                assert!(check_dns_servers(&network, NETWORK_SECTION).is_ok());
+            }
+        }
+
+        #[test]
+        fn fails_with_host_name() {
+            let (network, _) = load_quadlet(
+                "foo.network",
+                "[Network]\nDNS=8.8.8.8\nDNS=dns.example.com\n",
+            );
+
+            assert!(matches!(
+                check_dns_servers(&network, NETWORK_SECTION),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "DNS" && value == "dns.example.com"
+            ));
+        }
+
+        #[test]
+        fn accepts_lone_none() {
+            for data in [
+                "[Container]\nImage=foo\nDNS=none\n",
+                "[Container]\nImage=foo\nDNS=1.1.1.1\nDNS=\nDNS=none\n",
+            ] {
+                let (container, mut units_info_map) = load_quadlet("foo.container", data);
+
+                let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+                let args =
+                    unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+                assert!(args.windows(2).any(|w| w == ["--dns", "none"]), "{args:?}");
+                assert_eq!(args.iter().filter(|arg| *arg == "--dns").count(), 1);
+            }
+
+            let (pod, mut units_info_map) = load_quadlet("foo.pod", "[Pod]\nDNS=none\n");
+            let service = from_pod_unit(&pod, &mut units_info_map, false).unwrap();
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStartPre")
+                .unwrap()
+                .contains(" --dns none "));
+        }
+
+        #[test]
+        fn fails_with_none_and_other_servers() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nDNS=none\nDNS=1.1.1.1\n",
+            );
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::ConflictingValuesForKey(key, values)) if key == "DNS" && values == ["none", "1.1.1.1"]
+            ));
+
+            let (pod, mut units_info_map) =
+                load_quadlet("foo.pod", "[Pod]\nDNS=1.1.1.1\nDNS=none\n");
+            assert!(matches!(
+                from_pod_unit(&pod, &mut units_info_map, false),
+                Err(ConversionError::ConflictingValuesForKey(key, values)) if key == "DNS" && values == ["1.1.1.1", "none"]
+            ));
+        }
+
+        #[test]
+        fn is_checked_for_containers_and_networks() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo\nDNS=foo\n");
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::UnsupportedValueForKey(key, _)) if key == "DNS"
+            ));
+
+            let (network, mut units_info_map) = load_quadlet("foo.network", "[Network]\nDNS=foo\n");
+            assert!(matches!(
+                from_network_unit(&network, &mut units_info_map, false),
+                Err(ConversionError::UnsupportedValueForKey(key, _)) if key == "DNS"
+            ));
+
+            let (network, mut units_info_map) =
+                load_quadlet("foo.network", "[Network]\nDNS=8.8.8.8\nDNS=::1\n");
+            let service = from_network_unit(&network, &mut units_info_map, false).unwrap();
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .contains(" --dns 8.8.8.8 --dns ::1 "));
+        }
+    }
+
+    mod check_id_mapping {
+        use super::*;
+
+        #[test]
+        fn accepts_valid_mappings() {
+            for mapping in ["0:10000:65536", "0:10000", "+0:10000:1", "0:@1000:1"] {
+                assert!(check_id_mapping("UIDMap", mapping).is_ok(), "{mapping}");
+            }
+        }
+
+        #[test]
+        fn fails_with_malformed_mappings() {
+            for mapping in ["0:foo:65536", "0", "0:1:2:3", "@0:1:2", "0:1:", "-1:0:1"] {
+                assert!(
+                    matches!(
+                        check_id_mapping("UIDMap", mapping),
+                        Err(ConversionError::InvalidRemapUsers(msg)) if msg.contains(&format!("{mapping:?}"))
+                    ),
+                    "{mapping}"
+                );
+            }
+        }
+
+        #[test]
+        fn is_checked_for_uid_and_gid_maps() {
+            for key in ["UIDMap", "GIDMap"] {
+                let (container, mut units_info_map) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=foo\n{key}=0:foo:65536\n"),
+                );
+
+                assert!(matches!(
+                    from_container_unit(&container, &mut units_info_map, false),
+                    Err(ConversionError::InvalidRemapUsers(msg)) if msg.starts_with(&format!("invalid {key} "))
+                ));
+            }
+        }
+    }
+
+    mod check_secret_options {
+        use super::*;
+
+        #[test]
+        fn accepts_bare_secret() {
+            assert!(check_secret_options("mysecret", &SUPPORTED_SECRET_OPTIONS).is_ok());
+            assert!(check_secret_options("mysecret", &SUPPORTED_BUILD_SECRET_OPTIONS).is_ok());
+        }
+
+        #[test]
+        fn accepts_supported_options() {
+            for secret in [
+                "mysecret,type=env,target=MYSECRET",
+                "source=mysecret,type=mount,uid=1000,gid=1001,mode=777",
+            ] {
+                assert!(
+                    check_secret_options(secret, &SUPPORTED_SECRET_OPTIONS).is_ok(),
+                    "{secret}"
+                );
+            }
+            assert!(check_secret_options(
+                "id=mysecret,src=mysecret.txt",
+                &SUPPORTED_BUILD_SECRET_OPTIONS
+            )
+            .is_ok());
+        }
+
+        #[test]
+        fn fails_with_unknown_option() {
+            for (secret, option) in [
+                ("mysecret,typ=mount", "typ"),
+                ("source=mysecret,mount", "mount"),
+                ("mysecret,id=foo", "id"),
+            ] {
+                assert!(
+                    matches!(
+                        check_secret_options(secret, &SUPPORTED_SECRET_OPTIONS),
+                        Err(ConversionError::InvalidSecretOption(s, o, _)) if s == secret && o == option
+                    ),
+                    "{secret}"
+                );
+            }
+        }
+
+        #[test]
+        fn is_checked_when_converting() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nSecret=mysecret,typ=mount\n",
+            );
+            let (build, mut build_units_info_map) = load_quadlet(
+                "foo.build",
+                "[Build]\nImageTag=localhost/foo\nSetWorkingDirectory=unit\nSecret=id=foo,target=/x\n",
+            );
+
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::InvalidSecretOption(_, option, _)) if option == "typ"
+            ));
+            assert!(matches!(
+                from_build_unit(&build, &mut build_units_info_map, false),
+                Err(ConversionError::InvalidSecretOption(_, option, _)) if option == "target"
+            ));
+        }
+    }
+
+    mod check_timezone {
+        use super::*;
+
+        #[test]
+        fn passes_timezones_to_podman() {
+            for timezone in [
+                "Europe/Berlin",
+                "local",
+                "UTC",
+                "America/Argentina/Buenos_Aires",
+            ] {
+                let (container, mut units_info_map) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=localhost/foo\nTimezone={timezone}\n"),
+                );
+
+                let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+                let args =
+                    unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+                assert!(args.windows(2).any(|w| w == ["--tz", timezone]), "{args:?}");
+            }
+        }
+
+        #[test]
+        fn fails_with_path_traversal() {
+            for timezone in ["../../etc/passwd", "Europe/../../etc/shadow", ".."] {
+                let (container, mut units_info_map) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=localhost/foo\nTimezone={timezone}\n"),
+                );
+
+                assert!(
+                    matches!(
+                        from_container_unit(&container, &mut units_info_map, false),
+                        Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "Timezone" && value == timezone
+                    ),
+                    "{timezone}"
+                );
+            }
+        }
+    }
+
+    mod check_ulimits {
+        use super::*;
+
+        #[test]
+        fn accepts_valid_ulimits() {
+            for ulimit in [
+                "nofile=1024:2048",
+                "nofile=1024",
+                "nofile=host",
+                "core=unlimited",
+                "memlock=-1:-1",
+                "host",
+            ] {
+                let (container, _) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=foo\nUlimit={ulimit}\n"),
+                );
+
+                assert!(
+                    check_ulimits(&container, CONTAINER_SECTION).is_ok(),
+                    "{ulimit}"
+                );
+            }
+        }
+
+        #[test]
+        fn fails_with_malformed_ulimits() {
+            for ulimit in [
+                "nofilee=1024:2048",
+                "nofile",
+                "=1024",
+                "nofile=",
+                "nofile=1024:",
+                "nofile=lots",
+                "nofile=1024:2048:4096",
+                "nofile=-2",
+            ] {
+                let (container, _) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=foo\nUlimit=nproc=1\nUlimit={ulimit}\n"),
+                );
+
+                assert!(
+                    matches!(
+                        check_ulimits(&container, CONTAINER_SECTION),
+                        Err(ConversionError::InvalidUlimit(value)) if value == ulimit
+                    ),
+                    "{ulimit}"
+                );
+            }
+        }
+    }
+
+    mod validate_unit {
+        use super::*;
+
+        fn validate(path: &str, data: &str) -> Vec<ConversionError> {
+            let (unit_file, _) = load_quadlet(path, data);
+
+            validate_unit(
+                &unit_file,
+                &QuadletType::from_path(Path::new(path)).unwrap(),
+            )
+        }
+
+        #[test]
+        fn accepts_valid_units() {
+            for (path, data) in [
+                ("foo.artifact", "[Artifact]\nArtifact=localhost/foo\n"),
+                ("foo.build", "[Build]\nImageTag=localhost/foo\n"),
+                ("foo.build", "[Build]\nTag=localhost/foo\n"),
+                ("foo.container", "[Container]\nImage=localhost/foo\n"),
+                ("foo.container", "[Container]\nRootfs=/var/lib/foo\n"),
+                ("foo.image", "[Image]\nImage=localhost/foo\n"),
+                ("foo.kube", "[Kube]\nYaml=foo.yml\n"),
+                ("foo.network", "[Network]\n"),
+                ("foo.pod", "[Pod]\n"),
+                ("foo.volume", "[Volume]\n"),
+            ] {
+                let errors = validate(path, data);
+
+                assert!(errors.is_empty(), "{path}: {errors:?}");
+            }
+        }
+
+        #[test]
+        fn fails_with_container_without_image() {
+            assert!(matches!(
+                validate("foo.container", "[Container]\nExec=sleep\n").as_slice(),
+                [ConversionError::EmptyRequiredKey(key, file)] if key == "Image or Rootfs" && file == "foo.container"
+            ));
+        }
+
+        #[test]
+        fn fails_with_container_with_image_and_rootfs() {
+            assert!(matches!(
+                validate("foo.container", "[Container]\nImage=foo\nRootfs=/foo\n").as_slice(),
+                [ConversionError::MutuallyExclusiveKeys(a, b)] if a == "Image" && b == "Rootfs"
+            ));
+        }
+
+        #[test]
+        fn fails_with_image_without_image() {
+            assert!(matches!(
+                validate("foo.image", "[Image]\nArch=arm64\n").as_slice(),
+                [ConversionError::EmptyRequiredKey(key, file)] if key == "Image" && file == "foo.image"
+            ));
+        }
+
+        #[test]
+        fn fails_with_build_without_image_tag() {
+            assert!(matches!(
+                validate("foo.build", "[Build]\nFile=Containerfile\n").as_slice(),
+                [ConversionError::EmptyRequiredKey(key, file)] if key == "ImageTag" && file == "foo.build"
+            ));
+        }
+
+        #[test]
+        fn fails_with_kube_without_yaml() {
+            assert!(matches!(
+                validate("foo.kube", "[Kube]\n").as_slice(),
+                [ConversionError::EmptyRequiredKey(key, _)] if key == "Yaml"
+            ));
+        }
+
+        #[test]
+        fn reports_all_errors() {
+            let errors = validate("foo.image", "[Image]\nFoo=bar\n[Quadlet]\nBaz=qux\n");
+
+            assert_eq!(errors.len(), 3, "{errors:?}");
+            assert!(
+                matches!(&errors[0], ConversionError::EmptyRequiredKey(key, _) if key == "Image")
+            );
+            assert!(
+                matches!(&errors[1], ConversionError::UnknownKey(msg) if msg.contains("'Foo'"))
+            );
+            assert!(
+                matches!(&errors[2], ConversionError::UnknownKey(msg) if msg.contains("'Baz'"))
+            );
+        }
+
+        #[test]
+        fn is_run_by_converters() {
+            let (build, mut units_info_map) =
+                load_quadlet("foo.build", "[Build]\nImageTag=localhost/foo\nFoo=bar\n");
+
+            assert!(matches!(
+                from_build_unit(&build, &mut units_info_map, false),
+                Err(ConversionError::UnknownKey(_))
+            ));
+        }
+    }
+
+    mod check_for_unknown_keys {
+        use super::*;
+
+        #[test]
+        fn names_single_unknown_key() {
+            let (container, _) = load_quadlet("foo.container", "[Container]\nImage=foo\nFoo=bar\n");
+
+            assert!(matches!(
+                check_for_unknown_keys(&container, CONTAINER_SECTION, &SUPPORTED_CONTAINER_KEYS),
+                Err(ConversionError::UnknownKey(msg)) if msg == "unsupported key 'Foo' in group 'Container' in \"foo.container\""
+            ));
+        }
+
+        #[test]
+        fn accepts_supported_keys_in_any_case() {
+            let (container, _) = load_quadlet(
+                "foo.container",
+                "[Container]\nimage=foo\nIMAGE=bar\nNetwork=host\n",
+            );
+
+            assert!(check_for_unknown_keys(
+                &container,
+                CONTAINER_SECTION,
+                &SUPPORTED_CONTAINER_KEYS
+            )
+            .is_ok());
+        }
+
+        #[test]
+        fn names_all_unknown_keys() {
+            let (container, _) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nFoo=1\nBar=2\nFoo=3\nBaz=4\n",
+            );
+
+            assert!(matches!(
+                check_for_unknown_keys(&container, CONTAINER_SECTION, &SUPPORTED_CONTAINER_KEYS),
+                Err(ConversionError::UnknownKey(msg)) if msg == "unsupported keys 'Foo', 'Bar', 'Baz' in group 'Container' in \"foo.container\""
+            ));
+        }
+
+        #[test]
+        fn explains_set_working_directory_in_container() {
+            let (container, _) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nSetWorkingDirectory=unit\n",
+            );
+
+            assert!(matches!(
+                check_for_unknown_keys(&container, CONTAINER_SECTION, &SUPPORTED_CONTAINER_KEYS),
+                Err(ConversionError::UnknownKey(msg)) if msg == concat!(
+                    "unsupported key 'SetWorkingDirectory' in group 'Container' in \"foo.container\", ",
+                    "use WorkingDir= for the working directory inside the container ",
+                    "(SetWorkingDirectory= sets the host-side working directory of .build and .kube units)"
+                )
+            ));
+        }
+    }
+
+    mod derive_resource_name {
+        use super::*;
+
+        #[test]
+        fn prefixes_file_stem() {
+            let (network, _) = load_quadlet("foo.network", "[Network]\n");
+
+            assert_eq!(derive_resource_name(&network).unwrap(), "systemd-foo");
+        }
+
+        #[test]
+        fn trims_invalid_trailing_chars() {
+            let (network, _) = load_quadlet("foo..network", "[Network]\n");
+            let (volume, _) = load_quadlet("foo_ -.volume", "[Volume]\n");
+
+            assert_eq!(derive_resource_name(&network).unwrap(), "systemd-foo");
+            assert_eq!(derive_resource_name(&volume).unwrap(), "systemd-foo");
+        }
+
+        #[test]
+        fn fails_with_invalid_chars() {
+            let (volume, _) = load_quadlet("foo bar.volume", "[Volume]\n");
+
+            assert!(matches!(
+                derive_resource_name(&volume),
+                Err(ConversionError::InvalidResourceName(name)) if name == "systemd-foo bar"
+            ));
+        }
+    }
+
+    mod expand_resource_name_specifiers {
+        use super::*;
+
+        #[test]
+        fn expands_unit_name() {
+            let (volume, _) = load_quadlet("cache.volume", "[Volume]\n");
+
+            assert_eq!(
+                expand_resource_name_specifiers(&volume, "%N-data"),
+                "cache-data"
+            );
+            assert_eq!(
+                expand_resource_name_specifiers(&volume, "%N-%N"),
+                "cache-cache"
+            );
+        }
+
+        #[test]
+        fn keeps_other_specifiers() {
+            let (network, _) = load_quadlet("foo.network", "[Network]\n");
+
+            assert_eq!(
+                expand_resource_name_specifiers(&network, "%u-net"),
+                "%u-net"
+            );
+            assert_eq!(expand_resource_name_specifiers(&network, "%%N"), "%%N");
+            assert_eq!(expand_resource_name_specifiers(&network, "net%"), "net%");
+        }
+    }
+
+    mod handle_podman_args {
+        use super::*;
+
+        fn podman_args(data: &str) -> Vec<String> {
+            let (container, _) = load_quadlet("foo.container", data);
+            let mut podman = PodmanCommand::new();
+
+            handle_podman_args(&container, CONTAINER_SECTION, &mut podman);
+
+            podman.args()[1..].to_vec()
+        }
+
+        #[test]
+        fn keeps_nested_quotes_in_single_argument() {
+            let args = podman_args(
+                "[Container]\nPodmanArgs=--entrypoint \"sh -c \\\"echo hi\\\"\" 'a \"b\" c'\n",
+            );
+
+            assert_eq!(args, ["--entrypoint", "sh -c \"echo hi\"", "a \"b\" c"]);
+        }
+
+        #[test]
+        fn keeps_double_dash_separator() {
+            let args = podman_args("[Container]\nPodmanArgs=--foo -- --bar\n");
+
+            assert_eq!(args, ["--foo", "--", "--bar"]);
+        }
+
+        #[test]
+        fn ignores_empty_continuation_lines() {
+            let args =
+                podman_args("[Container]\nPodmanArgs=--foo \\\n  \\\n  --bar \\\n\nImage=foo\n");
+
+            assert_eq!(args, ["--foo", "--bar"]);
+        }
+
+        #[test]
+        fn survives_escaping_in_exec_start() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=localhost/foo\nPodmanArgs=--entrypoint \"sh -c \\\"echo hi\\\"\" -- ''\n",
+            );
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+            let exec_start = service
+                .lookup_last_value(SERVICE_SECTION, "ExecStart")
+                .unwrap();
+            let args = unescape_exec(exec_start.raw());
+            assert!(
+                args.ends_with(&[
+                    "--entrypoint".into(),
+                    "sh -c \"echo hi\"".into(),
+                    "--".into(),
+                    "".into(),
+                    "localhost/foo".into()
+                ]),
+                "{args:?}"
+            );
+        }
+    }
+
+    mod get_base_podman_command {
+        use super::*;
+
+        #[test]
+        fn has_no_log_level_by_default() {
+            let (container, _) = load_quadlet("foo.container", "[Container]\nImage=foo\n");
+
+            let podman = get_base_podman_command(&container, CONTAINER_SECTION);
+
+            assert!(podman.args[1..].is_empty());
+        }
+
+        #[test]
+        fn adds_debug_log_level_before_global_args() {
+            let (container, _) = load_quadlet(
+                "foo.container",
+                "[Quadlet]\nPodmanDebug=yes\n[Container]\nImage=foo\nGlobalArgs=--foo\n",
+            );
+
+            let podman = get_base_podman_command(&container, CONTAINER_SECTION);
+
+            assert_eq!(podman.args[1..], ["--log-level=debug", "--foo"]);
+        }
+
+        #[test]
+        fn adds_modules_and_global_args_in_declaration_order() {
+            let (container, _) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nGlobalArgs=--foo\nContainersConfModule=/etc/1.conf\nGlobalArgs=--bar --baz\nContainersConfModule=/etc/2.conf\n",
+            );
+
+            let podman = get_base_podman_command(&container, CONTAINER_SECTION);
+
+            assert_eq!(
+                podman.args[1..],
+                [
+                    "--module",
+                    "/etc/1.conf",
+                    "--module",
+                    "/etc/2.conf",
+                    "--foo",
+                    "--bar",
+                    "--baz"
+                ]
+            );
+        }
+
+        #[test]
+        fn adds_modules_and_global_args_before_subcommand() {
+            let global_args = "ContainersConfModule=/etc/1.conf\nContainersConfModule=/etc/2.conf\nGlobalArgs=--foo\nGlobalArgs=--bar\n";
+
+            for (path, data, subcommand) in [
+                ("foo.build", "[Build]\nImageTag=foo\nFile=/f\n", "build"),
+                ("foo.container", "[Container]\nImage=foo\n", "run"),
+                ("foo.image", "[Image]\nImage=foo\n", "image pull"),
+                ("foo.kube", "[Kube]\nYaml=/foo.yml\n", "kube play"),
+                ("foo.network", "[Network]\n", "network create"),
+                ("foo.pod", "[Pod]\n", "pod start"),
+                ("foo.volume", "[Volume]\n", "volume create"),
+            ] {
+                let (unit, mut units_info_map) =
+                    load_quadlet(path, &format!("{data}{global_args}"));
+
+                let service = match path.rsplit_once('.').unwrap().1 {
+                    "build" => from_build_unit(&unit, &mut units_info_map, false),
+                    "container" => from_container_unit(&unit, &mut units_info_map, false),
+                    "image" => from_image_unit(&unit, &mut units_info_map, false),
+                    "kube" => from_kube_unit(&unit, &mut units_info_map, false),
+                    "network" => from_network_unit(&unit, &mut units_info_map, false),
+                    "pod" => from_pod_unit(&unit, &mut units_info_map, false),
+                    "volume" => from_volume_unit(&unit, &mut units_info_map, false),
+                    _ => unreachable!(),
+                }
+                .unwrap();
+
+                let exec_start = service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap();
+                assert!(
+                    exec_start.contains(&format!(
+                        " --module /etc/1.conf --module /etc/2.conf --foo --bar {subcommand} "
+                    )),
+                    "unexpected ExecStart for {path}: {exec_start}"
+                );
+            }
+        }
+    }
+
+    mod handle_log_opt {
+        use super::*;
+
+        fn log_opts(log_opts: &str) -> Vec<String> {
+            let (container, _) = load_quadlet(
+                "/etc/containers/systemd/foo.container",
+                &format!("[Container]\nImage=foo\n{log_opts}"),
+            );
+            let mut podman = PodmanCommand::new();
+
+            handle_log_opt(&container, CONTAINER_SECTION, &mut podman);
+
+            podman.args()[1..].to_vec()
+        }
+
+        #[test]
+        fn keeps_absolute_path() {
+            assert_eq!(
+                log_opts("LogOpt=path=/var/log/foo.log\n"),
+                ["--log-opt", "path=/var/log/foo.log"]
+            );
+        }
+
+        #[test]
+        fn anchors_relative_path_at_unit_dir() {
+            assert_eq!(
+                log_opts("LogOpt=path=./container.log\nLogOpt=path=logs/../foo.log\n"),
+                [
+                    "--log-opt",
+                    "path=/etc/containers/systemd/container.log",
+                    "--log-opt",
+                    "path=/etc/containers/systemd/foo.log",
+                ]
+            );
+        }
+
+        #[test]
+        fn keeps_path_with_specifier() {
+            assert_eq!(
+                log_opts("LogOpt=path=%h/foo.log\n"),
+                ["--log-opt", "path=%h/foo.log"]
+            );
+        }
+
+        #[test]
+        fn passes_other_options_unchanged() {
+            assert_eq!(
+                log_opts("LogOpt=max-size=10m tag=./foo\nLogOpt=path=\n"),
+                [
+                    "--log-opt",
+                    "max-size=10m",
+                    "--log-opt",
+                    "tag=./foo",
+                    "--log-opt",
+                    "path=",
+                ]
+            );
+        }
+    }
+
+    mod handle_kill_mode {
+        use super::*;
+
+        #[test]
+        fn defaults_to_mixed() {
+            let (mut service, _) = load_quadlet("foo.container", "[Container]\nImage=foo\n");
+
+            handle_kill_mode(&mut service).unwrap();
+
+            assert_eq!(
+                service.lookup(SERVICE_SECTION, "KillMode"),
+                Some("mixed".into())
+            );
+        }
+
+        #[test]
+        fn keeps_supported_kill_modes() {
+            for kill_mode in ["mixed", "control-group"] {
+                let (mut service, _) = load_quadlet(
+                    "foo.container",
+                    &format!("[Service]\nKillMode={kill_mode}\n"),
+                );
+
+                handle_kill_mode(&mut service).unwrap();
+
+                assert_eq!(
+                    service.lookup_all(SERVICE_SECTION, "KillMode"),
+                    vec![kill_mode]
+                );
+            }
+        }
+
+        #[test]
+        fn fails_with_unsupported_kill_mode() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\n[Service]\nKillMode=process\n",
+            );
+
+            let err = from_container_unit(&container, &mut units_info_map, false).unwrap_err();
+
+            assert!(matches!(&err, ConversionError::InvalidKillMode(mode) if mode == "process"));
+            assert_eq!(
+                err.to_string(),
+                "invalid KillMode \"process\": only \"mixed\" and \"control-group\" are supported, with other modes container processes may outlive the service"
+            );
+        }
+
+        #[test]
+        fn is_checked_for_kube_units() {
+            let (kube, mut units_info_map) = load_quadlet(
+                "foo.kube",
+                "[Kube]\nYaml=/foo.yml\n[Service]\nKillMode=none\n",
+            );
+
+            assert!(matches!(
+                from_kube_unit(&kube, &mut units_info_map, false),
+                Err(ConversionError::InvalidKillMode(mode)) if mode == "none"
+            ));
+        }
+    }
+
+    mod handle_networks {
+        use super::*;
+
+        fn networks(container: &str) -> Result<Vec<String>, ConversionError> {
+            let (container, units_info_map) = load_quadlet("foo.container", container);
+            let mut service = SystemdUnit::new();
+            let mut podman = PodmanCommand::new();
+
+            handle_networks(
+                &container,
+                CONTAINER_SECTION,
+                &mut service,
+                &units_info_map,
+                &mut podman,
+            )
+            .map(|()| podman.args[1..].to_vec())
+        }
+
+        #[test]
+        fn fails_with_ip_for_host_network() {
+            assert!(matches!(
+                networks("[Container]\nImage=foo\nNetwork=host\nIP=10.0.0.2\n"),
+                Err(ConversionError::InvalidNetworkMode(network, key)) if network == "host" && key == "IP"
+            ));
+        }
+
+        #[test]
+        fn fails_with_network_alias_for_none_network() {
+            assert!(matches!(
+                networks("[Container]\nImage=foo\nNetwork=none\nNetworkAlias=foo\n"),
+                Err(ConversionError::InvalidNetworkMode(network, key)) if network == "none" && key == "NetworkAlias"
+            ));
+        }
+
+        #[test]
+        fn fails_with_ip6_for_container_network() {
+            assert!(matches!(
+                networks("[Container]\nImage=foo\nNetwork=container:bar\nIP6=fd00::2\n"),
+                Err(ConversionError::InvalidNetworkMode(network, key)) if network == "container:bar" && key == "IP6"
+            ));
+        }
+
+        #[test]
+        fn warns_about_publish_port_for_host_network() {
+            let (networks, records) = capture_logs(|| {
+                networks("[Container]\nImage=foo\nNetwork=host\nPublishPort=80:80\n")
+            });
+
+            assert_eq!(networks.unwrap(), ["--network", "host"]);
+            assert!(records.contains(&(
+                Level::Warn,
+                "PublishPort= has no effect with Network=host".into()
+            )));
+        }
+
+        #[test]
+        fn passes_network_with_uppercase_extension_as_network_name() {
+            assert_eq!(
+                networks("[Container]\nImage=foo\nNetwork=foo.Container\n").unwrap(),
+                ["--network", "foo.Container"]
+            );
+            assert_eq!(
+                networks("[Container]\nImage=foo\nNetwork=foo.Network\n").unwrap(),
+                ["--network", "foo.Network"]
+            );
+        }
+
+        #[test]
+        fn allows_network_settings_for_other_networks() {
+            assert_eq!(
+                networks("[Container]\nImage=foo\nNetwork=mynet\nIP=10.0.0.2\nNetworkAlias=foo\n")
+                    .unwrap(),
+                ["--network", "mynet"]
+            );
+        }
+
+        #[test]
+        fn skips_blank_network() {
+            let (mut container, units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo\n");
+            container.add(CONTAINER_SECTION, "Network", "   ");
+            let mut service = SystemdUnit::new();
+            let mut podman = PodmanCommand::new();
+
+            handle_networks(
+                &container,
+                CONTAINER_SECTION,
+                &mut service,
+                &units_info_map,
+                &mut podman,
+            )
+            .unwrap();
+
+            assert!(!podman.args.contains(&"--network".to_string()));
+        }
+
+        #[test]
+        fn trims_network_name() {
+            let (mut container, units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo\n");
+            container.add(CONTAINER_SECTION, "Network", " mynet ");
+            let mut service = SystemdUnit::new();
+            let mut podman = PodmanCommand::new();
+
+            handle_networks(
+                &container,
+                CONTAINER_SECTION,
+                &mut service,
+                &units_info_map,
+                &mut podman,
+            )
+            .unwrap();
+
+            assert_eq!(podman.args[1..], ["--network", "mynet"]);
+        }
+    }
+
+    mod handle_pod {
+        use super::*;
+
+        #[test]
+        fn fails_with_non_pod_unit() {
+            for pod in ["foo.container", "foo.Pod", "foo"] {
+                let (container, mut units_info_map) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=foo\nPod={pod}\n"),
+                );
+                let mut service = SystemdUnitFile::new();
+                let mut podman = PodmanCommand::new();
+
+                assert!(matches!(
+                    handle_pod(
+                        &container,
+                        &mut service,
+                        CONTAINER_SECTION,
+                        &mut units_info_map,
+                        &mut podman,
+                    ),
+                    Err(ConversionError::InvalidPod(p)) if p == pod
+                ));
+            }
+        }
+    }
+
+    mod handle_default_dependencies {
+        use super::*;
+
+        #[test]
+        fn depends_on_network_online_by_default() {
+            for is_user in [false, true] {
+                let (container, _) = load_quadlet("foo.container", "[Container]\nImage=foo\n");
+                let mut service = container.clone();
+
+                handle_default_dependencies(&mut service, is_user);
+
+                assert_eq!(
+                    service.lookup_all(UNIT_SECTION, "Wants"),
+                    vec!["network-online.target"]
+                );
+                assert_eq!(
+                    service.lookup_all(UNIT_SECTION, "After"),
+                    vec!["network-online.target"]
+                );
+            }
+        }
+
+        #[test]
+        fn omits_network_online_without_default_dependencies() {
+            let (container, _) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\n[Quadlet]\nDefaultDependencies=no\n",
+            );
+            let mut service = container.clone();
+
+            handle_default_dependencies(&mut service, false);
+
+            assert!(!service.has_key(UNIT_SECTION, "Wants"));
+            assert!(!service.has_key(UNIT_SECTION, "After"));
+        }
+
+        #[test]
+        fn is_a_supported_quadlet_key_for_all_unit_types() {
+            assert!(SUPPORTED_QUADLET_KEYS.contains(&"DefaultDependencies"));
+
+            for (path, data) in [
+                ("foo.container", "[Container]\nImage=foo\n"),
+                ("foo.network", "[Network]\n"),
+                ("foo.pod", "[Pod]\n"),
+                ("foo.volume", "[Volume]\n"),
+            ] {
+                let (unit, mut units_info_map) =
+                    load_quadlet(path, &format!("{data}[Quadlet]\nDefaultDependencies=no\n"));
+                let quadlet = QuadletUnitFile::from_unit_file(unit).unwrap();
+
+                let service = quadlet.convert(&mut units_info_map, false).unwrap();
+
+                assert!(
+                    !service
+                        .lookup_all_strv(UNIT_SECTION, "Wants")
+                        .contains(&"network-online.target".to_string()),
+                    "{path}"
+                );
+                assert!(
+                    !service
+                        .lookup_all_strv(UNIT_SECTION, "After")
+                        .contains(&"network-online.target".to_string()),
+                    "{path}"
+                );
+            }
+        }
+    }
+
+    mod handle_exec_reload {
+        use super::*;
+
+        fn exec_reload(container: &str) -> Option<String> {
+            let (container, _) = load_quadlet("foo.container", container);
+            let mut service = SystemdUnitFile::new();
+
+            handle_exec_reload(&container, CONTAINER_SECTION, &mut service).unwrap();
+
+            service
+                .lookup_last_value(SERVICE_SECTION, "ExecReload")
+                .map(|v| v.raw().to_string())
+        }
+
+        #[test]
+        fn does_nothing_without_reload_cmd() {
+            assert_eq!(exec_reload("[Container]\nImage=foo\n"), None);
+            assert_eq!(exec_reload("[Container]\nImage=foo\nReloadCmd=\n"), None);
+        }
+
+        #[test]
+        fn execs_reload_cmd_in_container() {
+            let exec_reload =
+                exec_reload("[Container]\nImage=foo\nReloadCmd=nginx -s reload\n").unwrap();
+
+            assert_eq!(
+                unescape_exec(&exec_reload)[1..],
+                ["exec", "--cidfile=%t/%N.cid", "nginx", "-s", "reload"]
+            );
+        }
+
+        #[test]
+        fn keeps_specifiers_and_quoting() {
+            let exec_reload =
+                exec_reload("[Container]\nImage=foo\nReloadCmd=/bin/sh -c \"echo reloading %n\"\n")
+                    .unwrap();
+
+            assert!(
+                exec_reload.ends_with(" exec --cidfile=%t/%N.cid /bin/sh -c \"echo reloading %n\"")
+            );
+            assert_eq!(
+                unescape_exec(&exec_reload)[1..],
+                [
+                    "exec",
+                    "--cidfile=%t/%N.cid",
+                    "/bin/sh",
+                    "-c",
+                    "echo reloading %n"
+                ]
+            );
+        }
+    }
+
+    mod handle_pull_policy {
+        use super::*;
+
+        #[test]
+        fn adds_pull_with_equal_sign_for_build() {
+            let (build, mut units_info_map) = load_quadlet(
+                "foo.build",
+                "[Build]\nImageTag=foo\nFile=/Containerfile\nPull=always\n",
+            );
+
+            let service = from_build_unit(&build, &mut units_info_map, false).unwrap();
+            let args = unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+
+            assert!(args.contains(&"--pull=always".to_string()));
+            assert!(!args.contains(&"--pull".to_string()));
+        }
+
+        #[test]
+        fn adds_policy_with_equal_sign_for_image() {
+            let (image, mut units_info_map) =
+                load_quadlet("foo.image", "[Image]\nImage=quay.io/foo\nPolicy=newer\n");
+
+            let service = from_image_unit(&image, &mut units_info_map, false).unwrap();
+            let args = unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+
+            assert!(args.contains(&"--policy=newer".to_string()));
+            assert!(!args.contains(&"--policy".to_string()));
+        }
+
+        #[test]
+        fn skips_empty_value() {
+            let (image, _) = load_quadlet("foo.image", "[Image]\nImage=quay.io/foo\nPolicy=\n");
+            let mut podman = PodmanCommand::new();
+
+            handle_pull_policy(&image, IMAGE_SECTION, "Policy", "--policy", &mut podman).unwrap();
+
+            assert!(podman.args[1..].is_empty());
+        }
+
+        #[test]
+        fn accepts_supported_policies() {
+            for policy in ["always", "missing", "never", "newer"] {
+                let (build, _) = load_quadlet(
+                    "foo.build",
+                    &format!("[Build]\nImageTag=foo\nPull={policy}\n"),
+                );
+                let mut podman = PodmanCommand::new();
+
+                handle_pull_policy(&build, BUILD_SECTION, "Pull", "--pull", &mut podman).unwrap();
+
+                assert_eq!(podman.args[1..], [format!("--pull={policy}")]);
+            }
+        }
+
+        #[test]
+        fn fails_with_unsupported_policy() {
+            let (build, mut build_units_info_map) = load_quadlet(
+                "foo.build",
+                "[Build]\nImageTag=foo\nFile=/Containerfile\nPull=alwyas\n",
+            );
+            let (container, mut container_units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo\nPull=mising\n");
+            let (image, mut image_units_info_map) =
+                load_quadlet("foo.image", "[Image]\nImage=quay.io/foo\nPolicy=true\n");
+
+            assert!(matches!(
+                from_build_unit(&build, &mut build_units_info_map, false),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "Pull" && value == "alwyas"
+            ));
+            assert!(matches!(
+                from_container_unit(&container, &mut container_units_info_map, false),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "Pull" && value == "mising"
+            ));
+            assert!(matches!(
+                from_image_unit(&image, &mut image_units_info_map, false),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "Policy" && value == "true"
+            ));
+        }
+    }
+
+    mod handle_unit_dependencies {
+        use super::*;
+
+        #[test]
+        fn translates_quadlet_units_to_service_names() {
+            let (kube, mut units_info_map) = load_quadlet(
+                "app.kube",
+                "[Unit]\nRequires=db.volume\nAfter=db.volume foo.service\n[Kube]\nYaml=/app.yml\n",
+            );
+            let (_, volume_units_info_map) = load_quadlet("db.volume", "[Volume]\n");
+            units_info_map.0.extend(volume_units_info_map.0);
+
+            let service = from_kube_unit(&kube, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                service.lookup_all(UNIT_SECTION, "Requires"),
+                vec!["db-volume.service"]
+            );
+            assert_eq!(
+                service.lookup_all_strv(UNIT_SECTION, "After"),
+                vec!["network-online.target", "db-volume.service", "foo.service"]
+            );
+        }
+
+        #[test]
+        fn translates_on_failure_on_success_and_upholds() {
+            let (container, mut units_info_map) = load_quadlet(
+                "app.container",
+                "[Unit]\nOnFailure=cleanup.container\nOnSuccess=cleanup.container\nUpholds=cleanup.container\n[Container]\nImage=localhost/app\n",
+            );
+            let (_, cleanup_units_info_map) = load_quadlet(
+                "cleanup.container",
+                "[Container]\nImage=localhost/cleanup\n",
+            );
+            units_info_map.0.extend(cleanup_units_info_map.0);
+            let mut service = container.clone();
+
+            handle_unit_dependencies(&mut service, &units_info_map).unwrap();
+
+            for key in ["OnFailure", "OnSuccess", "Upholds"] {
+                assert_eq!(
+                    service.lookup_all(UNIT_SECTION, key),
+                    vec!["cleanup.service"]
+                );
+            }
+        }
+
+        #[test]
+        fn keeps_other_dependencies_untouched() {
+            let (kube, units_info_map) = load_quadlet(
+                "app.kube",
+                "[Unit]\nRequires=foo.service\nRequires=bar.target\n",
+            );
+            let mut service = kube.clone();
+
+            handle_unit_dependencies(&mut service, &units_info_map).unwrap();
+
+            assert_eq!(service, kube);
+        }
+
+        #[test]
+        fn fails_with_unknown_quadlet_unit() {
+            let (kube, units_info_map) = load_quadlet("app.kube", "[Unit]\nRequires=db.volume\n");
+            let mut service = kube.clone();
+
+            assert!(matches!(
+                handle_unit_dependencies(&mut service, &units_info_map),
+                Err(ConversionError::UnitDependencyNotFound(dep)) if dep == "db.volume"
+            ));
+        }
+    }
+
+    mod handle_volumes {
+        use super::*;
+
+        fn volume_args(volume: &str) -> (Vec<String>, SystemdUnitFile) {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                &format!("[Container]\nImage=foo\nVolume={volume}\n"),
+            );
+            let (data_volume, data_units_info_map) = load_quadlet("data.volume", "[Volume]\n");
+            units_info_map.0.extend(data_units_info_map.0);
+            // sets the volume's resource name
+            from_volume_unit(&data_volume, &mut units_info_map, false).unwrap();
+            let mut service = SystemdUnitFile::new();
+            let mut podman = PodmanCommand::new();
+
+            handle_volumes(
+                &container,
+                CONTAINER_SECTION,
+                &mut service,
+                &units_info_map,
+                &mut podman,
+            )
+            .unwrap();
+
+            (podman.args()[1..].to_vec(), service)
+        }
+
+        #[test]
+        fn passes_anonymous_volume() {
+            assert_eq!(volume_args("/data").0, ["-v", "/data"]);
+        }
+
+        #[test]
+        fn passes_source_with_uppercase_extension_as_named_volume() {
+            let (args, service) = volume_args("data.Volume:/data");
+
+            assert_eq!(args, ["-v", "data.Volume:/data"]);
+            assert!(!service.has_section(UNIT_SECTION));
+        }
+
+        #[test]
+        fn passes_source_and_dest() {
+            assert_eq!(volume_args("/src:/dest").0, ["-v", "/src:/dest"]);
+        }
+
+        #[test]
+        fn passes_source_dest_and_options() {
+            assert_eq!(volume_args("/src:/dest:ro,z").0, ["-v", "/src:/dest:ro,z"]);
+        }
+
+        #[test]
+        fn keeps_colons_in_options() {
+            assert_eq!(
+                volume_args("/src:/dest:ro,idmap=uids=0-1000-10:gids=0-1000-10").0,
+                ["-v", "/src:/dest:ro,idmap=uids=0-1000-10:gids=0-1000-10"]
+            );
+        }
+
+        #[test]
+        fn resolves_volume_unit_with_relabel_options() {
+            for options in ["Z", "ro,z"] {
+                let (args, service) = volume_args(&format!("data.volume:/data:{options}"));
+
+                assert_eq!(args, ["-v", &format!("systemd-data:/data:{options}")]);
+                assert_eq!(
+                    service.lookup_all(UNIT_SECTION, "Requires"),
+                    vec!["data-volume.service"]
+                );
+            }
+        }
+    }
+
+    mod quad_replace_extension {
+        use super::*;
+
+        #[test]
+        fn replaces_extension() {
+            assert_eq!(
+                quad_replace_extension(Path::new("/etc/foo.container"), ".service", "", ""),
+                PathBuf::from("/etc/foo.service")
+            );
+        }
+
+        #[test]
+        fn replaces_only_last_extension_of_dotted_names() {
+            assert_eq!(
+                quad_replace_extension(Path::new("web.v2.container"), ".service", "", ""),
+                PathBuf::from("web.v2.service")
+            );
+            assert_eq!(
+                quad_replace_extension(Path::new("web.v2.network"), ".service", "", "-network"),
+                PathBuf::from("web.v2-network.service")
+            );
+            assert_eq!(
+                quad_replace_extension(Path::new("web.v2.volume"), "", "systemd-", ""),
+                PathBuf::from("systemd-web.v2")
+            );
+        }
+
+        #[test]
+        fn keeps_dotfile_name_as_base_name() {
+            assert_eq!(
+                quad_replace_extension(Path::new("/etc/.container"), ".service", "", "-x"),
+                PathBuf::from("/etc/.container-x.service")
+            );
+            assert_eq!(
+                quad_replace_extension(Path::new(".hidden.container"), ".service", "", ""),
+                PathBuf::from(".hidden.service")
+            );
+        }
+
+        #[test]
+        fn returns_paths_without_file_name_unchanged() {
+            assert_eq!(
+                quad_replace_extension(Path::new(""), ".service", "", ""),
+                PathBuf::from("")
+            );
+            assert_eq!(
+                quad_replace_extension(Path::new("/"), ".service", "", ""),
+                PathBuf::from("/")
+            );
+        }
+    }
+
+    mod resolve_container_mount_params {
+        use super::*;
+
+        #[test]
+        fn requires_mounts_for_absolute_bind_source() {
+            for source in ["src", "source"] {
+                let (container, mut units_info_map) =
+                    load_quadlet("foo.container", "[Container]\nImage=foo\n");
+                let mut service = SystemdUnitFile::new();
+
+                let mount = resolve_container_mount_params(
+                    &container,
+                    &mut service,
+                    format!("type=bind,{source}=/data,target=/data"),
+                    &mut units_info_map,
+                )
+                .unwrap();
+
+                assert_eq!(mount, "type=bind,source=/data,target=/data\n");
+                assert_eq!(
+                    service.lookup_all(UNIT_SECTION, "RequiresMountsFor"),
+                    vec!["/data"]
+                );
+            }
+        }
+
+        #[test]
+        fn requires_mounts_for_glob_source_dir() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo\n");
+            let mut service = SystemdUnitFile::new();
+
+            let mount = resolve_container_mount_params(
+                &container,
+                &mut service,
+                "type=glob,src=/data/*,target=/data".into(),
+                &mut units_info_map,
+            )
+            .unwrap();
+
+            assert_eq!(mount, "type=glob,source=/data/*,target=/data\n");
+            assert_eq!(
+                service.lookup_all(UNIT_SECTION, "RequiresMountsFor"),
+                vec!["/data"]
+            );
+        }
+
+        #[test]
+        fn resolves_relative_glob_source() {
+            let (container, mut units_info_map) = load_quadlet(
+                "/etc/containers/systemd/foo.container",
+                "[Container]\nImage=foo\n",
+            );
+            let mut service = SystemdUnitFile::new();
+
+            let mount = resolve_container_mount_params(
+                &container,
+                &mut service,
+                "type=glob,src=./data/*.conf,target=/data".into(),
+                &mut units_info_map,
+            )
+            .unwrap();
+
+            assert_eq!(
+                mount,
+                "type=glob,source=/etc/containers/systemd/data/*.conf,target=/data\n"
+            );
+            assert_eq!(
+                service.lookup_all(UNIT_SECTION, "RequiresMountsFor"),
+                vec!["/etc/containers/systemd/data"]
+            );
+        }
+
+        #[test]
+        fn resolves_relative_bind_source() {
+            let (container, mut units_info_map) = load_quadlet(
+                "/etc/containers/systemd/foo.container",
+                "[Container]\nImage=foo\n",
+            );
+            let mut service = SystemdUnitFile::new();
+
+            resolve_container_mount_params(
+                &container,
+                &mut service,
+                "type=bind,src=./data,target=/data".into(),
+                &mut units_info_map,
+            )
+            .unwrap();
+
+            assert_eq!(
+                service.lookup_all(UNIT_SECTION, "RequiresMountsFor"),
+                vec!["/etc/containers/systemd/data"]
+            );
+        }
+
+        #[test]
+        fn passes_tmpfs_through() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo\n");
+            let mut service = SystemdUnitFile::new();
+
+            let mount = resolve_container_mount_params(
+                &container,
+                &mut service,
+                "type=tmpfs,target=/tmp,tmpfs-size=64m".into(),
+                &mut units_info_map,
+            )
+            .unwrap();
+
+            assert_eq!(mount, "type=tmpfs,target=/tmp,tmpfs-size=64m");
+            assert!(!service.has_section(UNIT_SECTION));
+        }
+
+        #[test]
+        fn rejects_tmpfs_with_source() {
+            for source in ["src", "source"] {
+                let (container, mut units_info_map) =
+                    load_quadlet("foo.container", "[Container]\nImage=foo\n");
+                let mut service = SystemdUnitFile::new();
+
+                let result = resolve_container_mount_params(
+                    &container,
+                    &mut service,
+                    format!("type=tmpfs,{source}=/x,target=/tmp"),
+                    &mut units_info_map,
+                );
+
+                assert!(matches!(
+                    result,
+                    Err(ConversionError::InvalidMountSourceForType(t)) if t == "tmpfs"
+                ));
+            }
+        }
+    }
+
+    mod quadlet_type_of {
+        use super::*;
+
+        #[test]
+        fn finds_type_of_quadlet_unit_names() {
+            assert_eq!(quadlet_type_of("foo.volume"), Some(QuadletType::Volume));
+            assert_eq!(
+                quadlet_type_of("foo.bar.network"),
+                Some(QuadletType::Network)
+            );
+            assert_eq!(
+                quadlet_type_of("foo@.container"),
+                Some(QuadletType::Container)
+            );
+        }
+
+        #[test]
+        fn ignores_other_names() {
+            for name in ["foo", "foo.service", "foo.Container", ".network", "/data"] {
+                assert_eq!(quadlet_type_of(name), None, "{name:?}");
+            }
+        }
+    }
+
+    mod split_glob_pattern {
+        use super::*;
+
+        #[test]
+        fn splits_at_first_dir_with_glob_characters() {
+            assert_eq!(split_glob_pattern("/data/*"), ("/data", "*"));
+            assert_eq!(split_glob_pattern("/data/*/foo"), ("/data", "*/foo"));
+            assert_eq!(split_glob_pattern("/data/foo?/bar"), ("/data", "foo?/bar"));
+            assert_eq!(split_glob_pattern("./data/[ab]*"), ("./data", "[ab]*"));
+            assert_eq!(split_glob_pattern("/*.conf"), ("/", "*.conf"));
+            assert_eq!(split_glob_pattern("*.conf"), ("", "*.conf"));
+        }
+
+        #[test]
+        fn keeps_paths_without_glob_characters() {
+            assert_eq!(split_glob_pattern("/data/foo"), ("/data/foo", ""));
+        }
+    }
+
+    mod from_artifact_unit {
+        use super::*;
+
+        #[test]
+        fn pulls_artifact() {
+            let (artifact, mut units_info_map) = load_quadlet(
+                "foo.artifact",
+                "[Artifact]\nArtifact=quay.io/foo/bar:latest\nRetry=3\nTLSVerify=false\n",
+            );
+
+            let service = from_artifact_unit(&artifact, &mut units_info_map, false).unwrap();
+
+            assert_eq!(service.path(), &PathBuf::from("foo-artifact.service"));
+            assert_eq!(
+                unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap())[1..],
+                [
+                    "artifact",
+                    "pull",
+                    "--retry",
+                    "3",
+                    "--tls-verify=false",
+                    "quay.io/foo/bar:latest"
+                ]
+            );
+            assert_eq!(
+                service.lookup_last(SERVICE_SECTION, "Type"),
+                Some("oneshot".into())
+            );
+            assert_eq!(
+                service.lookup_last(SERVICE_SECTION, "RemainAfterExit"),
+                Some("yes".into())
+            );
+            assert!(service.has_key(X_ARTIFACT_SECTION, "Artifact"));
+            assert_eq!(
+                units_info_map.0[&OsString::from("foo.artifact")].resource_name,
+                "quay.io/foo/bar:latest"
+            );
+        }
+
+        #[test]
+        fn fails_with_empty_artifact() {
+            let (artifact, mut units_info_map) =
+                load_quadlet("foo.artifact", "[Artifact]\nArtifact=\n");
+
+            assert!(matches!(
+                from_artifact_unit(&artifact, &mut units_info_map, false),
+                Err(ConversionError::EmptyRequiredKey(key, file)) if key == "Artifact" && file == "foo.artifact"
+            ));
+        }
+    }
+
+    mod from_build_unit {
+        use super::*;
+
+        fn exec_start(build: &str) -> Result<String, ConversionError> {
+            let (build, mut units_info_map) =
+                load_quadlet("/etc/containers/systemd/foo.build", build);
+
+            from_build_unit(&build, &mut units_info_map, false).map(|service| {
+                service
+                    .lookup_last(SERVICE_SECTION, "ExecStart")
+                    .expect("should have ExecStart")
+            })
+        }
+
+        #[test]
+        fn accepts_tag_as_alias_for_image_tag() {
+            let exec_start =
+                exec_start("[Build]\nTag=localhost/foo\nFile=/Containerfile\n").unwrap();
+
+            assert!(exec_start.contains(" --tag localhost/foo "));
+        }
+
+        #[test]
+        fn uses_tag_for_resource_name() {
+            let (container, mut units_info_map) =
+                load_quadlet("bar.container", "[Container]\nImage=foo.build\n");
+            let (_, build_units_info_map) = load_quadlet(
+                "foo.build",
+                "[Build]\nTag=localhost/foo\nFile=/Containerfile\n",
+            );
+            units_info_map.0.extend(build_units_info_map.0);
+
+            assert_eq!(
+                units_info_map.0[&OsString::from("foo.build")].resource_name,
+                "localhost/foo"
+            );
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .ends_with(" localhost/foo"));
+        }
+
+        #[test]
+        fn prefers_image_tag_for_resource_name() {
+            let (_, units_info_map) = load_quadlet(
+                "foo.build",
+                "[Build]\nTag=localhost/bar\nImageTag=localhost/foo\nFile=/Containerfile\n",
+            );
+
+            assert_eq!(
+                units_info_map.0[&OsString::from("foo.build")].resource_name,
+                "localhost/foo"
+            );
+        }
+
+        #[test]
+        fn relative_file_with_unit_working_directory() {
+            let exec_start = exec_start(
+                "[Build]\nImageTag=foo\nFile=./Containerfile\nSetWorkingDirectory=unit\n",
+            )
+            .unwrap();
+
+            assert!(exec_start.ends_with(" --file ./Containerfile /etc/containers/systemd"));
+        }
+
+        #[test]
+        fn absolute_file_without_working_directory() {
+            let exec_start =
+                exec_start("[Build]\nImageTag=foo\nFile=/abs/Containerfile\n").unwrap();
+
+            assert!(exec_start.ends_with(" --file /abs/Containerfile"));
+        }
+
+        #[test]
+        fn url_file_without_working_directory() {
+            let exec_start =
+                exec_start("[Build]\nImageTag=foo\nFile=https://example.com/Containerfile\n")
+                    .unwrap();
+
+            assert!(exec_start.ends_with(" --file https://example.com/Containerfile"));
+        }
+
+        #[test]
+        fn fails_with_relative_file_without_working_directory() {
+            assert!(matches!(
+                exec_start("[Build]\nImageTag=foo\nFile=./Containerfile\n"),
+                Err(ConversionError::InvalidRelativeFile)
+            ));
+        }
+
+        #[test]
+        fn fails_with_empty_image_tag() {
+            let (build, mut units_info_map) =
+                load_quadlet("foo.build", "[Build]\nImageTag=\nFile=/Containerfile\n");
+
+            assert!(matches!(
+                from_build_unit(&build, &mut units_info_map, false),
+                Err(ConversionError::EmptyRequiredKey(key, file)) if key == "ImageTag" && file == "foo.build"
+            ));
+        }
+    }
+
+    mod from_container_unit {
+        use super::*;
+
+        #[test]
+        fn fails_with_empty_image_and_rootfs() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=\nRootfs=\n");
+
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::EmptyRequiredKey(key, file)) if key == "Image or Rootfs" && file == "foo.container"
+            ));
+        }
+
+        #[test]
+        fn keeps_quoted_env_values_with_spaces_intact() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=localhost/foo\nEnvironment=\"GREETING=hello world\" FAREWELL=\"good bye\"\n",
+            );
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+            let exec_start = service
+                .lookup_last_value(SERVICE_SECTION, "ExecStart")
+                .unwrap();
+            let args = unescape_exec(exec_start.raw());
+            assert!(
+                args.windows(2)
+                    .any(|w| w == ["--env", "GREETING=hello world"]),
+                "{args:?}"
+            );
+            assert!(
+                args.windows(2).any(|w| w == ["--env", "FAREWELL=good bye"]),
+                "{args:?}"
+            );
+        }
+
+        fn working_dir_warnings(working_dir: &str) -> Vec<String> {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                &format!("[Container]\nImage=localhost/foo\nWorkingDir={working_dir}\n"),
+            );
+
+            let (service, records) = capture_logs(|| {
+                from_container_unit(&container, &mut units_info_map, false).unwrap()
+            });
+            let args = unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+            assert!(args.windows(2).any(|w| w == ["--workdir", working_dir]));
+
+            records
+                .into_iter()
+                .filter(|(level, msg)| *level == Level::Warn && msg.starts_with("WorkingDir="))
+                .map(|(_, msg)| msg)
+                .collect()
+        }
+
+        #[test]
+        fn warns_about_relative_working_dir() {
+            assert_eq!(
+                working_dir_warnings("foo"),
+                ["WorkingDir=\"foo\" in \"foo.container\" is a relative path, it will be resolved inside the container image"]
+            );
+        }
+
+        #[test]
+        fn does_not_warn_about_absolute_working_dir() {
+            assert!(working_dir_warnings("/foo").is_empty());
+        }
+
+        #[test]
+        fn sets_timeout_stop_sec_from_stop_timeout() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo\nStopTimeout=30\n");
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                service.lookup(SERVICE_SECTION, "TimeoutStopSec"),
+                Some("30".into())
+            );
+        }
+
+        #[test]
+        fn keeps_user_provided_timeout_stop_sec() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nStopTimeout=30\n[Service]\nTimeoutStopSec=60\n",
+            );
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                service.lookup_all(SERVICE_SECTION, "TimeoutStopSec"),
+                vec!["60"]
+            );
+        }
+
+        fn image_unit_warnings(image: &str) -> Vec<String> {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=bar.image\n");
+            let (image, image_units_info_map) = load_quadlet("bar.image", image);
+            units_info_map.0.extend(image_units_info_map.0);
+            from_image_unit(&image, &mut units_info_map, false).unwrap();
+
+            let (_, records) = capture_logs(|| {
+                from_container_unit(&container, &mut units_info_map, false).unwrap()
+            });
+
+            records
+                .into_iter()
+                .filter(|(level, _)| *level == Level::Warn)
+                .map(|(_, msg)| msg)
+                .collect()
+        }
+
+        #[test]
+        fn warns_about_image_unit_with_all_tags() {
+            assert_eq!(
+                image_unit_warnings("[Image]\nImage=localhost/bar\nAllTags=true\n"),
+                ["\"foo.service\" references \"bar.image\", which pulls all tags of \"localhost/bar\" (AllTags=true), it's ambiguous which one will be used"]
+            );
+        }
+
+        #[test]
+        fn does_not_warn_about_image_unit_without_all_tags() {
+            assert!(image_unit_warnings("[Image]\nImage=localhost/bar\n").is_empty());
+            assert!(
+                image_unit_warnings("[Image]\nImage=localhost/bar\nAllTags=false\n").is_empty()
+            );
+        }
+
+        #[test]
+        fn parses_security_related_bools() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nReadOnly=yes\nNoNewPrivileges=true\nSecurityLabelDisable=off\n",
+            );
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+            let args = unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+
+            assert!(args.contains(&"--read-only".to_string()));
+            assert!(args.contains(&"--security-opt=no-new-privileges".to_string()));
+            assert!(!args.contains(&"label=disable".to_string()));
+        }
+
+        #[test]
+        fn fails_with_invalid_security_related_bools() {
+            for key in ["ReadOnly", "NoNewPrivileges", "SecurityLabelDisable"] {
+                let (container, mut units_info_map) = load_quadlet(
+                    "foo.container",
+                    &format!("[Container]\nImage=foo\n{key}=maybe\n"),
+                );
+
+                assert!(matches!(
+                    from_container_unit(&container, &mut units_info_map, false),
+                    Err(ConversionError::UnsupportedValueForKey(k, value)) if k == key && value == "maybe"
+                ));
+            }
+        }
+
+        fn pull_warnings(container: &str) -> Vec<String> {
+            let (container, mut units_info_map) = load_quadlet("foo.container", container);
+            let (_, build_units_info_map) = load_quadlet(
+                "base.build",
+                "[Build]\nImageTag=localhost/base\nFile=/Containerfile\n",
+            );
+            units_info_map.0.extend(build_units_info_map.0);
+
+            let (_, records) = capture_logs(|| {
+                from_container_unit(&container, &mut units_info_map, false).unwrap()
+            });
+
+            records
+                .into_iter()
+                .filter(|(level, msg)| *level == Level::Warn && msg.starts_with("Pull="))
+                .map(|(_, msg)| msg)
+                .collect()
+        }
+
+        #[test]
+        fn warns_about_pull_with_quadlet_image() {
+            assert_eq!(
+                pull_warnings("[Container]\nImage=base.build\nPull=always\n"),
+                ["Pull= in \"foo.container\" has no effect, because Image=\"base.build\" references a Quadlet unit"]
+            );
+        }
+
+        #[test]
+        fn does_not_warn_about_pull_with_regular_image() {
+            assert!(pull_warnings("[Container]\nImage=localhost/base\nPull=always\n").is_empty());
+            assert!(pull_warnings("[Container]\nImage=base.build\n").is_empty());
+        }
+
+        #[test]
+        fn fails_with_image_and_rootfs() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo.build\nRootfs=/r\n");
+
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::MutuallyExclusiveKeys(a, b)) if a == "Image" && b == "Rootfs"
+            ));
+        }
+
+        fn sd_notify_arg(container: &str) -> Result<String, ConversionError> {
+            let (container, mut units_info_map) = load_quadlet("foo.container", container);
+
+            from_container_unit(&container, &mut units_info_map, false).map(|service| {
+                unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap())
+                    .into_iter()
+                    .find(|arg| arg.starts_with("--sdnotify="))
+                    .expect("should have --sdnotify")
+            })
+        }
+
+        #[test]
+        fn derives_sd_notify_from_notify() {
+            for (notify, expected) in [
+                ("", "--sdnotify=conmon"),
+                ("Notify=false\n", "--sdnotify=conmon"),
+                ("Notify=true\n", "--sdnotify=container"),
+                ("Notify=healthy\n", "--sdnotify=healthy"),
+                ("Notify=no\n", "--sdnotify=conmon"),
+                ("Notify=yes\n", "--sdnotify=container"),
+                ("Notify=\n", "--sdnotify=conmon"),
+            ] {
+                assert_eq!(
+                    sd_notify_arg(&format!("[Container]\nImage=foo\n{notify}")).unwrap(),
+                    expected
+                );
+            }
+        }
+
+        #[test]
+        fn fails_with_unsupported_notify() {
+            assert!(matches!(
+                sd_notify_arg("[Container]\nImage=foo\nNotify=sometimes\n"),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "Notify" && value == "sometimes"
+            ));
+        }
+
+        #[test]
+        fn sd_notify_overrides_notify() {
+            assert_eq!(
+                sd_notify_arg("[Container]\nImage=foo\nNotify=true\nSdNotify=ignore\n").unwrap(),
+                "--sdnotify=ignore"
+            );
+        }
+
+        #[test]
+        fn accepts_all_supported_sd_notify_modes() {
+            for mode in SUPPORTED_SD_NOTIFY_MODES {
+                assert_eq!(
+                    sd_notify_arg(&format!(
+                        "[Container]\nImage=foo\nNotify=healthy\nSdNotify={mode}\n"
+                    ))
+                    .unwrap(),
+                    format!("--sdnotify={mode}")
+                );
+            }
+        }
+
+        #[test]
+        fn runs_in_foreground_with_sd_notify_ignore() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nImage=foo\nSdNotify=ignore\n");
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+            let args = unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+
+            assert!(args.contains(&"--sdnotify=ignore".to_string()));
+            assert!(!args.contains(&"-d".to_string()));
+            assert_eq!(service.lookup_all(SERVICE_SECTION, "Type"), vec!["exec"]);
+            assert!(!service.has_key(SERVICE_SECTION, "NotifyAccess"));
+        }
+
+        #[test]
+        fn fails_with_sd_notify_ignore_and_type_notify() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nSdNotify=ignore\n[Service]\nType=notify\n",
+            );
+
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::InvalidServiceTypeForSdNotify(mode)) if mode == "ignore"
+            ));
+        }
+
+        #[test]
+        fn fails_with_unsupported_sd_notify() {
+            assert!(matches!(
+                sd_notify_arg("[Container]\nImage=foo\nSdNotify=sometimes\n"),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "SdNotify" && value == "sometimes"
+            ));
+        }
+
+        #[test]
+        fn fails_with_unsupported_sd_notify_for_all_service_types() {
+            for service_type in ["oneshot", "exec", "simple"] {
+                let (container, mut units_info_map) = load_quadlet(
+                    "foo.container",
+                    &format!(
+                        "[Container]\nImage=foo\nSdNotify=sometimes\n[Service]\nType={service_type}\n"
+                    ),
+                );
+
+                assert!(
+                    matches!(
+                        from_container_unit(&container, &mut units_info_map, false),
+                        Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "SdNotify" && value == "sometimes"
+                    ),
+                    "{service_type}"
+                );
+            }
+        }
+
+        #[test]
+        fn runs_in_foreground_without_sd_notify_for_exec_and_simple() {
+            for service_type in ["exec", "simple"] {
+                let (container, mut units_info_map) = load_quadlet(
+                    "foo.container",
+                    &format!(
+                        "[Container]\nImage=foo\nNotify=true\n[Service]\nType={service_type}\n"
+                    ),
+                );
+
+                let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+                let args =
+                    unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+
+                assert!(!args.iter().any(|arg| arg.starts_with("--sdnotify")));
+                assert!(!args.contains(&"-d".to_string()));
+                assert_eq!(
+                    service.lookup_all(SERVICE_SECTION, "Type"),
+                    vec![service_type]
+                );
+                assert!(!service.has_key(SERVICE_SECTION, "NotifyAccess"));
+            }
+        }
+
+        #[test]
+        fn fails_with_unsupported_service_type() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\n[Service]\nType=forking\n",
+            );
+
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::InvalidServiceType(service_type)) if service_type == "forking"
+            ));
+        }
+
+        #[test]
+        fn adds_env_in_declaration_order() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nEnvironment=Z=1 A=2\nEnvironment=M=3 B=4\n",
+            );
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .contains(" --env Z=1 --env A=2 --env M=3 --env B=4 "));
+        }
+
+        #[test]
+        fn adds_dependencies_of_repeatedly_used_units_once() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[Container]\nImage=foo\nVolume=data.volume:/data\nMount=type=volume,src=data.volume,dst=/backup\nVolume=/srv:/srv\nMount=type=bind,src=/srv,dst=/mnt\n",
+            );
+            let (_, volume_units_info_map) = load_quadlet("data.volume", "[Volume]\n");
+            units_info_map.0.extend(volume_units_info_map.0);
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                service.lookup_all(UNIT_SECTION, "Requires"),
+                vec!["data-volume.service"]
+            );
+            assert_eq!(
+                service.lookup_all(UNIT_SECTION, "After"),
+                vec!["network-online.target", "data-volume.service"]
+            );
+            assert_eq!(
+                service.lookup_all(UNIT_SECTION, "RequiresMountsFor"),
+                vec!["%t/containers", "/srv"]
+            );
+        }
+
+        #[test]
+        fn keeps_custom_sections() {
+            let (container, mut units_info_map) = load_quadlet(
+                "foo.container",
+                "[X-MyTool]\nFoo=bar\nFoo=\"baz qux\"\n[Container]\nImage=foo\n",
+            );
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                service.section_entries("X-MyTool").collect::<Vec<_>>(),
+                container.section_entries("X-MyTool").collect::<Vec<_>>()
+            );
+            assert_eq!(
+                service
+                    .lookup_all_values_raw("X-MyTool", "Foo")
+                    .map(|v| v.raw())
+                    .collect::<Vec<_>>(),
+                ["bar", "\"baz qux\""]
+            );
+        }
+
+        #[test]
+        fn adds_podman_args_after_environment() {
+            let (container, mut units_info_map) = load_quadlet(
+                "/etc/containers/systemd/foo.container",
+                "[Container]\nImage=foo\nPodmanArgs=--env FOO=bar\nEnvironment=FOO=baz\nEnvironmentFile=/foo.env\n",
+            );
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+            let args = unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+            let position = |arg: &str| args.iter().position(|a| a == arg).unwrap();
+
+            assert!(position("/foo.env") < position("FOO=bar"));
+            assert!(position("FOO=baz") < position("FOO=bar"));
+            assert_eq!(position("FOO=bar") + 1, position("foo"));
+        }
+
+        #[test]
+        fn accepts_rootfs_options() {
+            for rootfs in ["/r:O", "/r:idmap", "/r:O:idmap", "/r:idmap=uids=0-1-10"] {
+                let (container, mut units_info_map) =
+                    load_quadlet("foo.container", &format!("[Container]\nRootfs={rootfs}\n"));
+
+                let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+                assert!(service
+                    .lookup_last(SERVICE_SECTION, "ExecStart")
+                    .unwrap()
+                    .ends_with(&format!(" --rootfs {rootfs}")));
+            }
+        }
+
+        fn env_file_args(path: &str, env_file: &str) -> Result<Vec<String>, ConversionError> {
+            let (container, mut units_info_map) = load_quadlet(
+                path,
+                &format!("[Container]\nImage=foo\nEnvironmentFile={env_file}\n"),
+            );
+
+            from_container_unit(&container, &mut units_info_map, false).map(|service| {
+                let args =
+                    unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+                args.windows(2)
+                    .filter(|w| w[0] == "--env-file")
+                    .map(|w| w[1].clone())
+                    .collect()
+            })
+        }
+
+        #[test]
+        fn keeps_absolute_environment_file() {
+            assert_eq!(
+                env_file_args("/etc/containers/systemd/foo.container", "/etc/foo.env").unwrap(),
+                ["/etc/foo.env"]
+            );
+        }
+
+        #[test]
+        fn resolves_environment_file_relative_to_unit() {
+            assert_eq!(
+                env_file_args("/etc/containers/systemd/foo.container", "foo.env").unwrap(),
+                ["/etc/containers/systemd/foo.env"]
+            );
+            assert_eq!(
+                env_file_args("/etc/containers/systemd/foo.container", "%h/foo.env").unwrap(),
+                ["%h/foo.env"]
+            );
+        }
+
+        #[test]
+        fn fails_with_unanchored_relative_environment_file() {
+            assert!(matches!(
+                env_file_args("systemd/foo.container", "foo.env"),
+                Err(ConversionError::InvalidRelativeEnvironmentFile(path)) if path == Path::new("systemd/foo.env")
+            ));
+        }
+
+        #[test]
+        fn empty_uid_map_in_dropin_resets_uid_maps() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("foo.container");
+            std::fs::write(
+                &path,
+                "[Container]\nImage=foo\nUIDMap=0:10000:10\nUIDMap=10:20000:10\n",
+            )
+            .unwrap();
+            std::fs::create_dir(dir.path().join("foo.container.d")).unwrap();
+            std::fs::write(
+                dir.path().join("foo.container.d/reset.conf"),
+                "[Container]\nUIDMap=\n",
+            )
+            .unwrap();
+
+            let mut container = SystemdUnitFile::load_from_path(&path).unwrap();
+            container.load_dropins_from([dir.path()]).unwrap();
+            let quadlet = QuadletUnitFile::from_unit_file(container.clone()).unwrap();
+            let mut units_info_map = UnitsInfoMap::from_quadlet_units(vec![quadlet]);
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+            let args = unescape_exec(&service.lookup_last(SERVICE_SECTION, "ExecStart").unwrap());
+
+            assert!(!args.iter().any(|arg| arg.starts_with("--uidmap")));
+        }
+
+        #[test]
+        fn keeps_literal_rootfs_path() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nRootfs=/var/lib/foo\n");
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .ends_with(" --rootfs /var/lib/foo"));
+            assert!(service.lookup_all(UNIT_SECTION, "Requires").is_empty());
+        }
+
+        #[test]
+        fn resolves_build_backed_rootfs() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nRootfs=bar.build\n");
+            let (_, build_units_info_map) = load_quadlet(
+                "bar.build",
+                "[Build]\nImageTag=localhost/bar\nFile=/Containerfile\n",
+            );
+            units_info_map.0.extend(build_units_info_map.0);
+
+            let service = from_container_unit(&container, &mut units_info_map, false).unwrap();
+
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .ends_with(" --rootfs localhost/bar"));
+            assert_eq!(
+                service.lookup_all(UNIT_SECTION, "Requires"),
+                vec!["bar-build.service"]
+            );
+            assert!(service
+                .lookup_all(UNIT_SECTION, "After")
+                .contains(&"bar-build.service".to_string()));
+        }
+
+        #[test]
+        fn fails_with_unknown_rootfs_option() {
+            let (container, mut units_info_map) =
+                load_quadlet("foo.container", "[Container]\nRootfs=/r:foo\n");
+
+            assert!(matches!(
+                from_container_unit(&container, &mut units_info_map, false),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "Rootfs" && value == "/r:foo"
+            ));
+        }
+    }
+
+    mod from_image_unit {
+        use super::*;
+
+        #[test]
+        fn fails_with_empty_image() {
+            let (image, mut units_info_map) = load_quadlet("foo.image", "[Image]\nImage=\n");
+
+            assert!(matches!(
+                from_image_unit(&image, &mut units_info_map, false),
+                Err(ConversionError::EmptyRequiredKey(key, file)) if key == "Image" && file == "foo.image"
+            ));
+        }
+    }
+
+    mod from_kube_unit {
+        use super::*;
+
+        #[test]
+        fn passes_supported_exit_code_propagation() {
+            for ecp in ["all", "any", "none"] {
+                let (kube, mut units_info_map) = load_quadlet(
+                    "foo.kube",
+                    &format!("[Kube]\nYaml=/foo.yml\nExitCodePropagation={ecp}\n"),
+                );
+
+                let service = from_kube_unit(&kube, &mut units_info_map, false).unwrap();
+
+                assert!(service
+                    .lookup_last(SERVICE_SECTION, "ExecStart")
+                    .unwrap()
+                    .contains(&format!(" --service-exit-code-propagation={ecp} ")));
+            }
+        }
+
+        #[test]
+        fn rejects_unsupported_exit_code_propagation() {
+            let (kube, mut units_info_map) = load_quadlet(
+                "foo.kube",
+                "[Kube]\nYaml=/foo.yml\nExitCodePropagation=some\n",
+            );
+
+            assert!(matches!(
+                from_kube_unit(&kube, &mut units_info_map, false),
+                Err(ConversionError::UnsupportedValueForKey(key, value)) if key == "ExitCodePropagation" && value == "some"
+            ));
+        }
+
+        #[test]
+        fn names_service_after_unit_by_default() {
+            let (kube, mut units_info_map) = load_quadlet("foo.kube", "[Kube]\nYaml=/foo.yml\n");
+
+            let service = from_kube_unit(&kube, &mut units_info_map, false).unwrap();
+
+            assert_eq!(service.path(), &PathBuf::from("foo.service"));
+        }
+
+        #[test]
+        fn uses_service_name_for_service_file() {
+            let (kube, mut units_info_map) =
+                load_quadlet("foo.kube", "[Kube]\nYaml=/foo.yml\nServiceName=custom\n");
+
+            assert_eq!(
+                units_info_map.0[&OsString::from("foo.kube")].get_service_file_name(),
+                "custom.service"
+            );
+
+            let service = from_kube_unit(&kube, &mut units_info_map, false).unwrap();
+
+            assert_eq!(service.path(), &PathBuf::from("custom.service"));
+        }
+
+        #[test]
+        fn uses_last_yaml() {
+            for yaml in [
+                "Yaml=/foo.yml\nYaml=/foo.yml\n",
+                "Yaml=/bar.yml\nYaml=\nYaml=/foo.yml\n",
+            ] {
+                let (kube, mut units_info_map) =
+                    load_quadlet("foo.kube", &format!("[Kube]\n{yaml}"));
+
+                let service = from_kube_unit(&kube, &mut units_info_map, false).unwrap();
+
+                assert!(service
+                    .lookup_last(SERVICE_SECTION, "ExecStart")
+                    .unwrap()
+                    .ends_with(" /foo.yml"));
+            }
+
+            // e.g. merged from a drop-in
+            let (mut kube, mut units_info_map) =
+                load_quadlet("foo.kube", "[Kube]\nYaml=/bar.yml\n");
+            kube.add(KUBE_SECTION, "Yaml", "/foo.yml");
+
+            let service = from_kube_unit(&kube, &mut units_info_map, false).unwrap();
+
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .ends_with(" /foo.yml"));
+        }
+
+        fn config_map_warnings(config_map: &Path) -> Vec<String> {
+            let (kube, mut units_info_map) = load_quadlet(
+                "/etc/containers/systemd/foo.kube",
+                &format!(
+                    "[Kube]\nYaml=/foo.yml\nConfigMap={}\n",
+                    config_map.display()
+                ),
+            );
+
+            let (_, records) =
+                capture_logs(|| from_kube_unit(&kube, &mut units_info_map, false).unwrap());
+
+            records
+                .into_iter()
+                .filter(|(level, msg)| *level == Level::Warn && msg.starts_with("ConfigMap"))
+                .map(|(_, msg)| msg)
+                .collect()
+        }
+
+        #[test]
+        fn warns_about_missing_config_map() {
+            let dir = tempfile::tempdir().unwrap();
+            let config_map = dir.path().join("missing.yml");
+
+            assert_eq!(
+                config_map_warnings(&config_map),
+                [format!("ConfigMap {config_map:?} in \"/etc/containers/systemd/foo.kube\" does not exist (yet)")]
+            );
+        }
+
+        #[test]
+        fn does_not_warn_about_existing_config_map() {
+            let dir = tempfile::tempdir().unwrap();
+            let config_map = dir.path().join("cm.yml");
+            std::fs::write(&config_map, "").unwrap();
+
+            assert!(config_map_warnings(&config_map).is_empty());
+        }
+
+        #[test]
+        fn fails_with_empty_yaml() {
+            let (kube, mut units_info_map) = load_quadlet("foo.kube", "[Kube]\nYaml=\n");
+
+            assert!(matches!(
+                from_kube_unit(&kube, &mut units_info_map, false),
+                Err(ConversionError::EmptyRequiredKey(key, file)) if key == "Yaml" && file == "foo.kube"
+            ));
+        }
+
+        #[test]
+        fn fails_with_working_directory_relative_to_missing_yaml() {
+            let (kube, mut units_info_map) =
+                load_quadlet("foo.kube", "[Kube]\nSetWorkingDirectory=yaml\n");
+
+            assert!(matches!(
+                handle_set_working_directory(&kube, &mut SystemdUnitFile::new(), KUBE_SECTION),
+                Err(ConversionError::EmptyRequiredKey(key, file)) if key == "Yaml" && file == "foo.kube"
+            ));
+            assert!(from_kube_unit(&kube, &mut units_info_map, false).is_err());
+        }
+    }
+
+    mod from_network_unit {
+        use super::*;
+
+        #[test]
+        fn expands_specifiers_in_network_name() {
+            let (network, mut units_info_map) =
+                load_quadlet("app.network", "[Network]\nNetworkName=%N-net\n");
+
+            let service = from_network_unit(&network, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                units_info_map.0[&OsString::from("app.network")].resource_name,
+                "app-net"
+            );
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .ends_with(" app-net"));
+        }
+    }
+
+    mod from_pod_unit {
+        use super::*;
+
+        #[test]
+        fn restarts_on_failure_by_default() {
+            let (pod, mut units_info_map) = load_quadlet("foo.pod", "[Pod]\n");
+
+            let service = from_pod_unit(&pod, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                service.lookup_all(SERVICE_SECTION, "Restart"),
+                vec!["on-failure"]
+            );
+        }
+
+        #[test]
+        fn keeps_user_provided_restart() {
+            let (pod, mut units_info_map) =
+                load_quadlet("foo.pod", "[Pod]\n[Service]\nRestart=always\n");
+
+            let service = from_pod_unit(&pod, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                service.lookup_all(SERVICE_SECTION, "Restart"),
+                vec!["always"]
+            );
+        }
+
+        #[test]
+        fn depends_on_quadlet_network_and_volume_services() {
+            let (pod, mut units_info_map) = load_quadlet(
+                "foo.pod",
+                "[Pod]\nNetwork=net.network\nVolume=data.volume:/data\n",
+            );
+            let (network, network_units_info_map) = load_quadlet("net.network", "[Network]\n");
+            let (volume, volume_units_info_map) = load_quadlet("data.volume", "[Volume]\n");
+            units_info_map.0.extend(network_units_info_map.0);
+            units_info_map.0.extend(volume_units_info_map.0);
+            // the pod's dependencies get converted first, which names their resources
+            from_network_unit(&network, &mut units_info_map, false).unwrap();
+            from_volume_unit(&volume, &mut units_info_map, false).unwrap();
+
+            let service = from_pod_unit(&pod, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                service.lookup_all(UNIT_SECTION, "Requires"),
+                vec!["net-network.service", "data-volume.service"]
+            );
+            assert_eq!(
+                service.lookup_all_strv(UNIT_SECTION, "After"),
+                vec![
+                    "network-online.target",
+                    "net-network.service",
+                    "data-volume.service"
+                ]
+            );
+        }
+    }
+
+    mod from_volume_unit {
+        use super::*;
+
+        #[test]
+        fn expands_specifiers_in_volume_name() {
+            let (volume, mut units_info_map) =
+                load_quadlet("cache.volume", "[Volume]\nVolumeName=%N-data\n");
+
+            let service = from_volume_unit(&volume, &mut units_info_map, false).unwrap();
+
+            assert_eq!(
+                units_info_map.0[&OsString::from("cache.volume")].resource_name,
+                "cache-data"
+            );
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .ends_with(" cache-data"));
+        }
+
+        #[test]
+        fn adds_labels_in_declaration_order() {
+            let (volume, mut units_info_map) =
+                load_quadlet("foo.volume", "[Volume]\nLabel=zeta=1\nLabel=alpha=2 mu=3\n");
+
+            let service = from_volume_unit(&volume, &mut units_info_map, false).unwrap();
+
+            assert!(service
+                .lookup_last(SERVICE_SECTION, "ExecStart")
+                .unwrap()
+                .ends_with(" --label zeta=1 --label alpha=2 --label mu=3 systemd-foo"));
+        }
+    }
 }
@@ -1,15 +1,17 @@
+use std::collections::HashSet;
 use std::ffi::OsStr;
 use std::io::ErrorKind;
 use std::os::unix::prelude::OsStrExt;
 use std::path::{Path, PathBuf};
 use std::{env, fs};
 
-use log::{debug, info};
+use log::{debug, info, warn};
 use walkdir::WalkDir;
 
 use super::constants::*;
 
 use super::RuntimeError;
+use crate::systemd_unit::uncompressed_path;
 
 pub(crate) struct UnitFiles {
     inner: Box<dyn Iterator<Item = Result<fs::DirEntry, RuntimeError>>>,
@@ -29,20 +31,41 @@ impl UnitFiles {
                 Ok(file) => file,
                 Err(e) => {
                     return Some(Err(RuntimeError::Io(
-                        format!("Can't read directory entry"),
+                        "Can't read directory entry".to_string(),
                         e,
                     )))
                 }
             };
 
-            if SUPPORTED_EXTENSIONS
+            // e.g. `foo.container.gz` with the "gzip" feature
+            let unit_path = uncompressed_path(&file.path());
+            if !SUPPORTED_EXTENSIONS
                 .map(OsStr::new)
-                .contains(&file.path().extension().unwrap_or(OsStr::new("")))
+                .contains(&unit_path.extension().unwrap_or(OsStr::new("")))
             {
-                Some(Ok(file))
-            } else {
-                None
+                return None;
             }
+
+            // symlinked units are included if they (eventually) point to a regular file
+            if file.file_type().is_ok_and(|t| t.is_symlink()) {
+                match fs::metadata(file.path()) {
+                    Ok(metadata) if metadata.is_file() => {}
+                    Ok(_) => {
+                        debug!(
+                            "Ignoring {:?}: symlink doesn't point to a file",
+                            file.path()
+                        );
+                        return None;
+                    }
+                    Err(e) => {
+                        // e.g. dangling symlinks or symlink loops
+                        debug!("Ignoring {:?}: can't resolve symlink: {e}", file.path());
+                        return None;
+                    }
+                }
+            }
+
+            Some(Ok(file))
         });
 
         Ok(UnitFiles {
@@ -70,29 +93,42 @@ impl UnitSearchDirs {
         UnitSearchDirsBuilder {
             // Allow overdiding source dir, this is mainly for the CI tests
             dirs: env::var("QUADLET_UNIT_DIRS").ok().map(|unit_dirs_env| {
-                env::split_paths(&unit_dirs_env)
-                    .map(PathBuf::from)
-                    .collect()
+                env::split_paths(&unit_dirs_env).collect()
             }),
             recursive: false,
             rootless: false,
         }
     }
 
+    // Searches the directories listed in the `QUADLET_UNIT_DIRS` environment variable (separated
+    // by ":") instead of the system (or user) directories, if it's set
     pub(crate) fn from_env_or_system() -> UnitSearchDirsBuilder {
-        if let Some(quadlet_unit_dirs) = env::var("QUADLET_UNIT_DIRS").ok() {
-            if !quadlet_unit_dirs.is_empty() {
-                return Self::from_env();
-            }
-        }
+        let mut builder = Self::from_env();
+
+        builder.dirs = builder
+            .dirs
+            .take()
+            .filter(|dirs| dirs.iter().any(|p| !p.as_os_str().is_empty()))
+            .map(|dirs| {
+                dirs.into_iter()
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .filter(|p| {
+                        if p.is_dir() {
+                            true
+                        } else {
+                            warn!(
+                                "Ignoring {p:?} from QUADLET_UNIT_DIRS: not an existing directory"
+                            );
+                            false
+                        }
+                    })
+                    .collect()
+            });
 
-        UnitSearchDirsBuilder {
-            dirs: None,
-            recursive: false,
-            rootless: false,
-        }
+        builder
     }
 
+    #[allow(clippy::new_ret_no_self, dead_code)]
     pub(crate) fn new(dirs: Vec<PathBuf>) -> UnitSearchDirsBuilder {
         UnitSearchDirsBuilder {
             dirs: Some(dirs),
@@ -101,7 +137,7 @@ impl UnitSearchDirs {
         }
     }
 
-    pub(crate) fn iter(&self) -> UnitSearchDirsIterator {
+    pub(crate) fn iter(&self) -> UnitSearchDirsIterator<'_> {
         UnitSearchDirsIterator {
             inner: self.0.iter(),
         }
@@ -236,11 +272,7 @@ impl UnitSearchDirsBuilder {
         }
     }
 
-    fn subdirs_for_search_dir(
-        &self,
-        path: PathBuf,
-        filter_fn: Option<&Box<dyn Fn(&walkdir::DirEntry, bool) -> bool>>,
-    ) -> Vec<PathBuf> {
+    fn subdirs_for_search_dir(&self, path: PathBuf, filter_fn: Option<&FilterFn>) -> Vec<PathBuf> {
         let path = if path.is_symlink() {
             match path.read_link() {
                 Ok(path) => path,
@@ -262,9 +294,19 @@ impl UnitSearchDirsBuilder {
             return dirs;
         }
 
+        // symlinked sub directories are followed, but each directory is only visited once
+        // (i.e. symlink loops are skipped)
+        let mut visited: HashSet<PathBuf> = HashSet::new();
+
         for entry in WalkDir::new(&path)
+            .follow_links(true)
             .into_iter()
-            .filter_entry(|e| e.path().is_dir())
+            // drop-in directories (e.g. foo.container.d) are handled separately
+            .filter_entry(|e| {
+                e.path().is_dir()
+                    && !(e.depth() > 0 && is_dropin_dir(e.path()))
+                    && fs::canonicalize(e.path()).is_ok_and(|p| visited.insert(p))
+            })
         {
             match entry {
                 Err(e) => debug!("Error occurred walking sub directories {path:?}: {e}"),
@@ -284,43 +326,52 @@ impl UnitSearchDirsBuilder {
     }
 }
 
-fn get_non_numeric_filter_func<'a>(
+// e.g. `foo.container.d` or the type-wide `container.d`, but not e.g. `apps.d`
+fn is_dropin_dir(path: &Path) -> bool {
+    if path.extension().is_none_or(|ext| ext != "d") {
+        return false;
+    }
+
+    let unit_name = Path::new(path.file_stem().unwrap_or_default());
+    let unit_type = unit_name.extension().unwrap_or(unit_name.as_os_str());
+
+    SUPPORTED_EXTENSIONS.map(OsStr::new).contains(&unit_type)
+}
+
+fn get_non_numeric_filter_func(
     resolved_unit_dir_admin_user: PathBuf,
     system_user_dir_level: usize,
-) -> Box<dyn Fn(&walkdir::DirEntry, bool) -> bool + 'a> {
-    return Box::new(move |entry, _rootless| -> bool {
+) -> FilterFn {
+    Box::new(move |entry, _rootless| -> bool {
         // when running in rootless, only recrusive walk directories that are non numeric
         // ignore sub dirs under the user directory that may correspond to a user id
         if entry
             .path()
             .starts_with(resolved_unit_dir_admin_user.clone())
         {
-            if entry.path().components().count() > system_user_dir_level {
-                if !entry
+            if entry.path().components().count() > system_user_dir_level
+                && !entry
                     .path()
                     .components()
-                    .last()
+                    .next_back()
                     .expect("path should have enough components")
                     .as_os_str()
                     .as_bytes()
                     .iter()
                     .all(|b| b.is_ascii_digit())
-                {
-                    return true;
-                }
+            {
+                return true;
             }
         } else {
             return true;
         }
 
         false
-    });
+    })
 }
 
-fn get_user_level_filter_func<'a>(
-    resolved_unit_dir_admin_user: PathBuf,
-) -> Box<dyn Fn(&walkdir::DirEntry, bool) -> bool + 'a> {
-    return Box::new(move |entry, rootless| -> bool {
+fn get_user_level_filter_func(resolved_unit_dir_admin_user: PathBuf) -> FilterFn {
+    Box::new(move |entry, rootless| -> bool {
         // if quadlet generator is run rootless, do not recurse other user sub dirs
         // if quadlet generator is run as root, ignore users sub dirs
         if entry
@@ -335,7 +386,7 @@ fn get_user_level_filter_func<'a>(
         }
 
         false
-    });
+    })
 }
 
 pub(crate) struct UnitSearchDirsIterator<'a> {
@@ -354,6 +405,54 @@ impl<'a> Iterator for UnitSearchDirsIterator<'a> {
 mod tests {
     use super::*;
 
+    mod unit_files {
+        use super::*;
+        use std::os;
+
+        fn file_names(dir: &Path) -> Vec<PathBuf> {
+            let mut names: Vec<PathBuf> = UnitFiles::new(dir)
+                .expect("should read dir")
+                .map(|f| f.expect("should be a valid entry").file_name().into())
+                .collect();
+            names.sort();
+            names
+        }
+
+        #[test]
+        fn includes_symlinks_to_unit_files() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let other_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let target = other_dir.path().join("target.container");
+            fs::write(&target, "[Container]\n").expect("cannot write file");
+            fs::write(temp_dir.path().join("foo.container"), "[Container]\n")
+                .expect("cannot write file");
+            os::unix::fs::symlink(&target, temp_dir.path().join("linked.container"))
+                .expect("cannot create symlink");
+
+            assert_eq!(
+                file_names(temp_dir.path()),
+                [PathBuf::from("foo.container"), "linked.container".into()]
+            );
+        }
+
+        #[test]
+        fn skips_dangling_and_looping_symlinks() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let dir = temp_dir.path();
+            fs::write(dir.join("foo.container"), "[Container]\n").expect("cannot write file");
+            os::unix::fs::symlink(
+                dir.join("missing.container"),
+                dir.join("dangling.container"),
+            )
+            .expect("cannot create symlink");
+            os::unix::fs::symlink(dir.join("loop.container"), dir.join("loop.container"))
+                .expect("cannot create symlink");
+            os::unix::fs::symlink(dir, dir.join("dir.container")).expect("cannot create symlink");
+
+            assert_eq!(file_names(dir), [PathBuf::from("foo.container")]);
+        }
+    }
+
     mod unit_search_dirs {
         use super::*;
 
@@ -411,7 +510,7 @@ mod tests {
                             .expect("config dir is not a valid UTF-8 string")
                     ),
                     format!("/etc/containers/systemd/users/{}", users::get_current_uid()),
-                    format!("/etc/containers/systemd/users"),
+                    "/etc/containers/systemd/users".to_string(),
                 ]
                 .iter()
                 .map(PathBuf::from)
@@ -486,6 +585,92 @@ mod tests {
             }
         }
 
+        mod from_env_or_system {
+            use super::*;
+
+            #[test]
+            #[serial_test::serial]
+            fn uses_dirs_from_env_var_in_order() {
+                // remember global state
+                let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+                let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+                let first_dir = temp_dir.path().join("first");
+                let second_dir = temp_dir.path().join("second");
+                fs::create_dir(&first_dir).expect("cannot create first dir");
+                fs::create_dir(&second_dir).expect("cannot create second dir");
+
+                env::set_var(
+                    "QUADLET_UNIT_DIRS",
+                    env::join_paths([&second_dir, &first_dir]).unwrap(),
+                );
+
+                let search_dirs = UnitSearchDirs::from_env_or_system().rootless(false).build();
+
+                assert_eq!(search_dirs.dirs(), &[second_dir, first_dir]);
+
+                // restore global setate
+                match _quadlet_unit_dirs {
+                    Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                    Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+                }
+            }
+
+            #[test]
+            #[serial_test::serial]
+            fn ignores_missing_dirs_and_files_from_env_var() {
+                // remember global state
+                let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+                let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+                let existing_dir = temp_dir.path().join("existing");
+                let missing_dir = temp_dir.path().join("missing");
+                let file = temp_dir.path().join("file");
+                fs::create_dir(&existing_dir).expect("cannot create existing dir");
+                fs::write(&file, "").expect("cannot create file");
+
+                env::set_var(
+                    "QUADLET_UNIT_DIRS",
+                    env::join_paths([&missing_dir, &file, &existing_dir]).unwrap(),
+                );
+
+                let search_dirs = UnitSearchDirs::from_env_or_system().rootless(false).build();
+
+                assert_eq!(search_dirs.dirs(), &[existing_dir]);
+
+                // restore global setate
+                match _quadlet_unit_dirs {
+                    Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                    Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+                }
+            }
+
+            #[test]
+            #[serial_test::serial]
+            fn doesnt_search_system_dirs_with_env_var() {
+                // remember global state
+                let _quadlet_unit_dirs = env::var("QUADLET_UNIT_DIRS");
+
+                let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+                env::set_var("QUADLET_UNIT_DIRS", temp_dir.path());
+
+                for rootless in [false, true] {
+                    let search_dirs = UnitSearchDirs::from_env_or_system()
+                        .rootless(rootless)
+                        .recursive(true)
+                        .build();
+
+                    assert_eq!(search_dirs.dirs(), &[temp_dir.path()], "{rootless}");
+                }
+
+                // restore global setate
+                match _quadlet_unit_dirs {
+                    Ok(val) => env::set_var("QUADLET_UNIT_DIRS", val),
+                    Err(_) => env::remove_var("QUADLET_UNIT_DIRS"),
+                }
+            }
+        }
+
         mod new {
             use super::*;
             use std::os;
@@ -501,6 +686,37 @@ mod tests {
                 assert_eq!(UnitSearchDirs::new(dirs).build().0, expected);
             }
 
+            #[test]
+            fn skips_dropin_dirs() {
+                let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+                let sub_dir = temp_dir.path().join("web");
+                fs::create_dir_all(temp_dir.path().join("foo.container.d"))
+                    .expect("cannot create drop-in dir");
+                fs::create_dir_all(sub_dir.join("bar.container.d"))
+                    .expect("cannot create nested drop-in dir");
+
+                let dirs = vec![temp_dir.path().into()];
+
+                let expected = [temp_dir.path(), sub_dir.as_path()];
+
+                assert_eq!(UnitSearchDirs::new(dirs).build().0, expected);
+            }
+
+            #[test]
+            fn keeps_dirs_not_belonging_to_units() {
+                let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+                let sub_dir = temp_dir.path().join("apps.d");
+                fs::create_dir_all(&sub_dir).expect("cannot create sub dir");
+                fs::create_dir_all(temp_dir.path().join("container.d"))
+                    .expect("cannot create drop-in dir");
+
+                let dirs = vec![temp_dir.path().into()];
+
+                let expected = [temp_dir.path(), sub_dir.as_path()];
+
+                assert_eq!(UnitSearchDirs::new(dirs).build().0, expected);
+            }
+
             #[test]
             fn should_follow_symlinks() {
                 // setup
@@ -521,6 +737,23 @@ mod tests {
                 // cleanup
                 fs::remove_dir_all(temp_dir.path()).expect("cannot remove temp dir");
             }
+
+            #[test]
+            fn follows_symlinked_sub_dirs_but_skips_loops() {
+                let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+                let other_dir = tempfile::tempdir().expect("cannot create temp dir");
+                let linked_dir = temp_dir.path().join("linked");
+                os::unix::fs::symlink(other_dir.path(), &linked_dir)
+                    .expect("cannot create symlink");
+                os::unix::fs::symlink(temp_dir.path(), temp_dir.path().join("loop"))
+                    .expect("cannot create symlink");
+
+                let dirs = vec![temp_dir.path().into()];
+
+                let expected = [temp_dir.path(), linked_dir.as_path()];
+
+                assert_eq!(UnitSearchDirs::new(dirs).build().0, expected);
+            }
         }
     }
 }
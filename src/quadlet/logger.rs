@@ -1,56 +1,73 @@
+use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io::{stderr, Write};
 use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
 use std::process;
 use std::sync::atomic::AtomicBool;
 use std::sync::Mutex;
 
-use log::{debug, Level, Metadata, Record};
+use log::{Level, LevelFilter, Metadata, Record};
 
 pub(crate) struct KmsgLogger {
-    pub(crate) debug_enabled: bool,
+    pub(crate) max_level: LevelFilter,
     pub(crate) dry_run: bool,
     kmsg_file: Mutex<Option<File>>,
     pub(crate) kmsg_enabled: AtomicBool,
+    kmsg_path: PathBuf,
+    // messages go here if they aren't (or can't be) logged to kmsg
+    fallback: Mutex<Box<dyn Write + Send>>,
 }
 
 impl KmsgLogger {
     pub(crate) fn init(self) -> Result<(), log::SetLoggerError> {
-        let max_log_level = if self.debug_enabled {
-            log::LevelFilter::Debug
-        } else {
-            log::LevelFilter::Info
-        };
+        let max_log_level = self.max_level;
+
+        // rootless generators often can't write to kmsg, make sure nothing gets lost from the start
+        if self.kmsg_enabled.load(std::sync::atomic::Ordering::SeqCst) {
+            let mut kmsg_file = self.kmsg_file.lock().expect("cannot lock file for logging");
+            self.open_kmsg(&mut kmsg_file);
+        }
 
         log::set_boxed_logger(Box::new(self)).map(|()| log::set_max_level(max_log_level))
     }
 
     pub(crate) fn new() -> Self {
         Self {
-            debug_enabled: false,
+            max_level: LevelFilter::Info,
             dry_run: false,
             kmsg_file: Mutex::new(None),
             kmsg_enabled: AtomicBool::new(true),
+            kmsg_path: PathBuf::from("/dev/kmsg"),
+            fallback: Mutex::new(Box::new(stderr())),
         }
     }
 
-    fn log(&self, record: &Record) {
+    fn format_message(level: Level, args: &fmt::Arguments) -> String {
         // every now and then I thinks, wouldn't it by nice if we could use `format_args!()` here ...
         // ... yes, it would ... but we can't: https://github.com/rust-lang/rust/issues/92698
-        let msg = format!(
-            "quadlet-rs-generator[{}]: {} - {}\n",
-            process::id(),
-            record.level(),
-            record.args()
-        );
+        format!(
+            "quadlet-rs-generator[{}]: {level} - {args}\n",
+            process::id()
+        )
+    }
+
+    fn log(&self, record: &Record) {
+        let msg = Self::format_message(record.level(), record.args());
 
         if !self.log_to_kmsg(&msg) || self.dry_run {
-            stderr()
-                .write_all(msg.as_bytes())
-                .expect("couldn't write to STDERR");
+            self.log_to_fallback(&msg);
         }
     }
 
+    fn log_to_fallback(&self, msg: &str) {
+        self.fallback
+            .lock()
+            .expect("cannot lock fallback for logging")
+            .write_all(msg.as_bytes())
+            .expect("couldn't write to STDERR");
+    }
+
     fn log_to_kmsg(&self, msg: &str) -> bool {
         if !self.kmsg_enabled.load(std::sync::atomic::Ordering::SeqCst) {
             return false;
@@ -58,16 +75,8 @@ impl KmsgLogger {
 
         let mut kmsg_file = self.kmsg_file.lock().expect("cannot lock file for logging");
 
-        if kmsg_file.is_none() {
-            *kmsg_file = match OpenOptions::new().write(true).mode(0o644).open("/dev/kmsg") {
-                Ok(f) => Some(f),
-                Err(e) => {
-                    self.kmsg_enabled
-                        .store(false, std::sync::atomic::Ordering::SeqCst);
-                    debug!("Deactivated logging to /dev/kmsg: {e}");
-                    return false;
-                }
-            };
+        if kmsg_file.is_none() && !self.open_kmsg(&mut kmsg_file) {
+            return false;
         }
 
         if let Some(file) = kmsg_file.as_mut() {
@@ -79,16 +88,41 @@ impl KmsgLogger {
 
         true
     }
+
+    // Deactivates logging to kmsg for good, if it can't be opened
+    fn open_kmsg(&self, kmsg_file: &mut Option<File>) -> bool {
+        match OpenOptions::new()
+            .write(true)
+            .mode(0o644)
+            .open(&self.kmsg_path)
+        {
+            Ok(f) => {
+                *kmsg_file = Some(f);
+                true
+            }
+            Err(e) => {
+                self.kmsg_enabled
+                    .store(false, std::sync::atomic::Ordering::SeqCst);
+                // the logger may not be installed yet, so this can't go through `log::info!()`
+                log::Log::log(
+                    self,
+                    &Record::builder()
+                        .level(Level::Info)
+                        .args(format_args!(
+                            "Deactivated logging to {:?}, logging to STDERR instead: {e}",
+                            self.kmsg_path
+                        ))
+                        .build(),
+                );
+                false
+            }
+        }
+    }
 }
 
 impl log::Log for KmsgLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level()
-            <= if self.debug_enabled {
-                Level::Debug
-            } else {
-                Level::Info
-            }
+        metadata.level() <= self.max_level
     }
 
     fn log(&self, record: &Record) {
@@ -101,3 +135,228 @@ impl log::Log for KmsgLogger {
         // no need to flush here, because we use write_all()
     }
 }
+
+// Maps systemd's log levels (i.e. `SYSTEMD_LOG_LEVEL`, by name or number) to the ones of `log`,
+// which has no equivalents for the most severe ones
+pub(crate) fn parse_systemd_log_level(level: &str) -> Option<LevelFilter> {
+    match level.trim() {
+        "emerg" | "alert" | "crit" | "err" | "0" | "1" | "2" | "3" => Some(LevelFilter::Error),
+        "warning" | "4" => Some(LevelFilter::Warn),
+        "notice" | "info" | "5" | "6" => Some(LevelFilter::Info),
+        "debug" | "7" => Some(LevelFilter::Debug),
+        _ => None,
+    }
+}
+
+// Captures log messages, so tests can make assertions about them.
+#[cfg(test)]
+pub(crate) mod test_logger {
+    use std::cell::RefCell;
+    use std::sync::Once;
+
+    use log::{Level, Metadata, Record};
+
+    thread_local! {
+        // tests run in parallel threads, so keep records separate
+        static RECORDS: RefCell<Vec<(Level, String)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    struct TestLogger;
+
+    impl log::Log for TestLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            RECORDS.with(|records| {
+                records
+                    .borrow_mut()
+                    .push((record.level(), record.args().to_string()))
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: TestLogger = TestLogger;
+    static INIT: Once = Once::new();
+
+    pub(crate) fn capture_logs<R>(f: impl FnOnce() -> R) -> (R, Vec<(Level, String)>) {
+        INIT.call_once(|| {
+            log::set_logger(&LOGGER).expect("could not initialize test logger");
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+
+        RECORDS.with(|records| records.borrow_mut().clear());
+        let result = f();
+        let records = RECORDS.with(|records| records.take());
+
+        (result, records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod parse_systemd_log_level {
+        use super::*;
+
+        #[test]
+        fn maps_names_and_numbers() {
+            for (levels, expected) in [
+                (["emerg", "0"], LevelFilter::Error),
+                (["alert", "1"], LevelFilter::Error),
+                (["crit", "2"], LevelFilter::Error),
+                (["err", "3"], LevelFilter::Error),
+                (["warning", "4"], LevelFilter::Warn),
+                (["notice", "5"], LevelFilter::Info),
+                (["info", "6"], LevelFilter::Info),
+                (["debug", "7"], LevelFilter::Debug),
+            ] {
+                for level in levels {
+                    assert_eq!(parse_systemd_log_level(level), Some(expected), "{level}");
+                }
+            }
+        }
+
+        #[test]
+        fn fails_with_unknown_level() {
+            for level in ["", "trace", "warn", "8"] {
+                assert_eq!(parse_systemd_log_level(level), None, "{level}");
+            }
+        }
+    }
+
+    mod kmsg_logger {
+        use super::*;
+        use std::io;
+        use std::sync::Arc;
+
+        // collects everything written to the fallback
+        #[derive(Clone, Default)]
+        struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+        impl SharedBuffer {
+            fn contents(&self) -> String {
+                String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+            }
+        }
+
+        impl Write for SharedBuffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        fn enabled_levels(systemd_log_level: &str) -> Vec<Level> {
+            let logger = KmsgLogger {
+                max_level: parse_systemd_log_level(systemd_log_level).unwrap(),
+                ..KmsgLogger::new()
+            };
+
+            [
+                Level::Error,
+                Level::Warn,
+                Level::Info,
+                Level::Debug,
+                Level::Trace,
+            ]
+            .into_iter()
+            .filter(|level| log::Log::enabled(&logger, &Metadata::builder().level(*level).build()))
+            .collect()
+        }
+
+        #[test]
+        fn info_level_suppresses_debug() {
+            assert_eq!(
+                enabled_levels("info"),
+                [Level::Error, Level::Warn, Level::Info]
+            );
+        }
+
+        #[test]
+        fn err_level_suppresses_warnings() {
+            assert_eq!(enabled_levels("err"), [Level::Error]);
+        }
+
+        #[test]
+        fn falls_back_when_kmsg_is_unwritable() {
+            // directories can't be opened for writing (not even by root)
+            let kmsg_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let fallback = SharedBuffer::default();
+            let logger = KmsgLogger {
+                kmsg_path: kmsg_dir.path().into(),
+                fallback: Mutex::new(Box::new(fallback.clone())),
+                ..KmsgLogger::new()
+            };
+
+            for msg in ["first", "second"] {
+                log::Log::log(
+                    &logger,
+                    &Record::builder()
+                        .level(Level::Warn)
+                        .args(format_args!("{msg}"))
+                        .build(),
+                );
+            }
+
+            assert!(!logger
+                .kmsg_enabled
+                .load(std::sync::atomic::Ordering::SeqCst));
+            let lines: Vec<String> = fallback
+                .contents()
+                .lines()
+                .map(|line| line.split_once(": ").unwrap().1.to_string())
+                .collect();
+            assert_eq!(
+                lines,
+                [
+                    format!(
+                        "INFO - Deactivated logging to {:?}, logging to STDERR instead: Is a directory (os error 21)",
+                        kmsg_dir.path()
+                    ),
+                    "WARN - first".into(),
+                    "WARN - second".into(),
+                ]
+            );
+        }
+
+        #[test]
+        fn respects_log_level_when_falling_back() {
+            let kmsg_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let fallback = SharedBuffer::default();
+            let logger = KmsgLogger {
+                max_level: parse_systemd_log_level("err").unwrap(),
+                kmsg_path: kmsg_dir.path().into(),
+                fallback: Mutex::new(Box::new(fallback.clone())),
+                ..KmsgLogger::new()
+            };
+
+            for level in [Level::Warn, Level::Error] {
+                log::Log::log(
+                    &logger,
+                    &Record::builder()
+                        .level(level)
+                        .args(format_args!("{level}"))
+                        .build(),
+                );
+            }
+
+            assert!(!logger
+                .kmsg_enabled
+                .load(std::sync::atomic::Ordering::SeqCst));
+            let lines: Vec<String> = fallback
+                .contents()
+                .lines()
+                .map(|line| line.split_once(": ").unwrap().1.to_string())
+                .collect();
+            assert_eq!(lines, ["ERROR - ERROR"]);
+        }
+    }
+}
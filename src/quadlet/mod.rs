@@ -3,6 +3,7 @@ pub(crate) mod convert;
 pub mod iterators;
 pub(crate) mod logger;
 pub(crate) mod podman_command;
+pub(crate) mod summary;
 
 use convert::quad_replace_extension;
 use log::warn;
@@ -11,15 +12,18 @@ use regex_lite::Regex;
 use crate::systemd_unit;
 use crate::systemd_unit::PathBufExt;
 use crate::systemd_unit::SystemdUnitFile;
+use crate::systemd_unit::{INSTALL_SECTION, SERVICE_SECTION, UNIT_SECTION};
 
 pub(crate) use self::constants::*;
 pub(crate) use self::iterators::*;
 
 use std::collections::HashMap;
 use std::env;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::io;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum RuntimeError {
@@ -29,17 +33,29 @@ pub(crate) enum RuntimeError {
     Io(String, #[source] io::Error),
     #[error("{0}: {1}")]
     Conversion(String, #[source] ConversionError),
+    #[error("Missing --unit-type argument for reading from stdin")]
+    CliMissingUnitType,
     #[error("unsupported file type {0:?}")]
     UnsupportedQuadletType(PathBuf),
+    #[error("unsupported unit type {0:?}")]
+    UnsupportedUnitType(String),
+    #[error("PODMAN={0:?} is not an absolute path and wasn't found in $PATH")]
+    PodmanNotFound(String),
 }
 
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 pub(crate) enum ConversionError {
+    #[error("conflicting values for key {0}: {1:?}")]
+    ConflictingValuesForKey(String, Vec<String>),
+    #[error("no {0} key specified in {1:?}")]
+    EmptyRequiredKey(String, OsString),
     #[error("requested Quadlet image {0:?} was not found")]
     ImageNotFound(String),
     #[error("internal error while processing {0} {1:?}")]
     InternalQuadletError(String, OsString),
+    #[error("invalid AddHost {0:?}: should be <hostname>[;<hostname>...]:<ip|host-gateway>")]
+    InvalidAddHost(String),
     #[error("key Options can't be used without Device")]
     InvalidDeviceOptions,
     #[error("key Type can't be used without Device")]
@@ -48,7 +64,7 @@ pub(crate) enum ConversionError {
     InvalidGroup,
     #[error("{0}")]
     InvalidImageOrRootfs(String),
-    #[error("invalid KillMode {0:?}")]
+    #[error("invalid KillMode {0:?}: only \"mixed\" and \"control-group\" are supported, with other modes container processes may outlive the service")]
     InvalidKillMode(String),
     #[error("{0}")]
     InvalidMountCsv(#[from] csv::Error),
@@ -56,42 +72,60 @@ pub(crate) enum ConversionError {
     InvalidMountFormat(String),
     #[error("source parameter does not include a value")]
     InvalidMountSource,
+    #[error("mount type {0:?} doesn't take a source")]
+    InvalidMountSourceForType(String),
+    #[error("key {1} can't be used with Network={0}")]
+    InvalidNetworkMode(String, String),
     #[error("extra options are not supported when joining another container's network")]
     InvalidNetworkOptions,
     #[error("pod {0:?} is not Quadlet based")]
     InvalidPod(String),
     #[error("invalid port format {0:?}")]
     InvalidPortFormat(String),
+    #[error("relative path {0:?} in EnvironmentFile key can't be resolved to an absolute path")]
+    InvalidRelativeEnvironmentFile(PathBuf),
     #[error("relative path in File key requires SetWorkingDirectory key to be set")]
     InvalidRelativeFile,
     #[error("{0}")]
     InvalidRemapUsers(String),
+    #[error("invalid resource name {0:?}: must match [a-zA-Z0-9][a-zA-Z0-9_.-]*")]
+    InvalidResourceName(String),
     #[error("cannot get the resource name of {0}")]
     InvalidResourceNameIn(String),
+    #[error("invalid option {1:?} in Secret {0:?}, supported options are: {2}")]
+    InvalidSecretOption(String, String, String),
+    #[error("invalid ServiceName {0:?}: must be a unit name without a directory")]
+    InvalidServiceName(String),
     #[error("invalid service Type {0:?}")]
     InvalidServiceType(String),
+    #[error("service Type=notify can't be used with SdNotify={0}: nothing would notify systemd that the container is ready")]
+    InvalidServiceTypeForSdNotify(String),
     #[error("SetWorkingDirectory={0:?} is only supported in .{1} files")]
     InvalidSetWorkingDirectory(String, String),
     #[error("{0}")]
     InvalidSubnet(String),
+    #[error("invalid Ulimit {0:?}: must be \"host\" or of the form name=soft[:hard], with a known name and numeric or \"unlimited\" limits")]
+    InvalidUlimit(String),
     #[error("{0}")]
     Io(#[from] io::Error),
-    #[error("no ImageTag key specified")]
-    NoImageTagKeySpecified,
+    #[error("{0} and {1} are mutually exclusive, but both are set")]
+    MutuallyExclusiveKeys(String, String),
     #[error("no File key specified")]
     NoFileKeySpecified,
     #[error("neither SetWorkingDirectory, nor File key specified")]
     NoSetWorkingDirectoryNorFileKeySpecified,
-    #[error("no Yaml key specified")]
-    NoYamlKeySpecified,
     #[error("failed parsing unit file: {0}")]
     Parsing(#[from] systemd_unit::Error),
     #[error("Quadlet pod unit {0:?} does not exist")]
     PodNotFound(String),
     #[error("requested Quadlet source {0:?} was not found")]
     SourceNotFound(String),
+    #[error("unable to translate dependency on {0:?}: no such Quadlet unit")]
+    UnitDependencyNotFound(String),
     #[error("{0}")]
     UnknownKey(String),
+    #[error("mount type {0:?} is not supported by podman run, it can only be used with RUN --mount in the Containerfile of a .build unit")]
+    UnsupportedMountType(String),
     #[error("unsupported value for {0:?}: {1:?}")]
     UnsupportedValueForKey(String, String),
 }
@@ -108,6 +142,7 @@ impl From<systemd_unit::IoError> for ConversionError {
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 #[non_exhaustive]
 pub(crate) enum QuadletType {
+    Artifact,
     Build,
     Container,
     Image,
@@ -118,12 +153,66 @@ pub(crate) enum QuadletType {
 }
 
 impl QuadletType {
+    // Like systemd unit types, extensions are case-sensitive (i.e. `foo.Container` is no Quadlet unit)
+    pub(crate) fn from_extension(extension: &OsStr) -> Option<QuadletType> {
+        extension.to_str()?.parse().ok()
+    }
+
     pub(crate) fn from_path(path: &Path) -> Result<QuadletType, RuntimeError> {
-        match path
-            .extension()
-            .map(|e| e.to_str().unwrap_or_default())
-            .unwrap_or_default()
-        {
+        path.extension()
+            .and_then(QuadletType::from_extension)
+            .ok_or_else(|| RuntimeError::UnsupportedQuadletType(path.to_path_buf()))
+    }
+
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            QuadletType::Artifact => "artifact",
+            QuadletType::Build => "build",
+            QuadletType::Container => "container",
+            QuadletType::Image => "image",
+            QuadletType::Kube => "kube",
+            QuadletType::Network => "network",
+            QuadletType::Pod => "pod",
+            QuadletType::Volume => "volume",
+        }
+    }
+
+    // The name of the section holding the type specific keys (e.g. "Container")
+    pub(crate) fn section_name(&self) -> &'static str {
+        match self {
+            QuadletType::Artifact => ARTIFACT_SECTION,
+            QuadletType::Build => BUILD_SECTION,
+            QuadletType::Container => CONTAINER_SECTION,
+            QuadletType::Image => IMAGE_SECTION,
+            QuadletType::Kube => KUBE_SECTION,
+            QuadletType::Network => NETWORK_SECTION,
+            QuadletType::Pod => POD_SECTION,
+            QuadletType::Volume => VOLUME_SECTION,
+        }
+    }
+}
+
+// The keys supported in the type specific section (see `QuadletType::section_name()`), e.g. for
+// validating files without converting them
+pub(crate) fn supported_keys(typ: QuadletType) -> &'static [&'static str] {
+    match typ {
+        QuadletType::Artifact => &SUPPORTED_ARTIFACT_KEYS,
+        QuadletType::Build => &SUPPORTED_BUILD_KEYS,
+        QuadletType::Container => &SUPPORTED_CONTAINER_KEYS,
+        QuadletType::Image => &SUPPORTED_IMAGE_KEYS,
+        QuadletType::Kube => &SUPPORTED_KUBE_KEYS,
+        QuadletType::Network => &SUPPORTED_NETWORK_KEYS,
+        QuadletType::Pod => &SUPPORTED_POD_KEYS,
+        QuadletType::Volume => &SUPPORTED_VOLUME_KEYS,
+    }
+}
+
+impl FromStr for QuadletType {
+    type Err = RuntimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "artifact" => Ok(QuadletType::Artifact),
             "build" => Ok(QuadletType::Build),
             "container" => Ok(QuadletType::Container),
             "image" => Ok(QuadletType::Image),
@@ -131,7 +220,7 @@ impl QuadletType {
             "network" => Ok(QuadletType::Network),
             "pod" => Ok(QuadletType::Pod),
             "volume" => Ok(QuadletType::Volume),
-            _ => Err(RuntimeError::UnsupportedQuadletType(path.to_path_buf())),
+            _ => Err(RuntimeError::UnsupportedUnitType(s.into())),
         }
     }
 }
@@ -156,15 +245,39 @@ impl QuadletUnitFile {
         unit_file: SystemdUnitFile,
     ) -> Result<QuadletUnitFile, RuntimeError> {
         let quadlet_type = QuadletType::from_path(unit_file.path())?;
+
+        QuadletUnitFile::with_type(unit_file, quadlet_type)
+    }
+
+    // Use when the type can't be derived from the unit file's extension (e.g. when read from stdin)
+    pub(crate) fn with_type(
+        unit_file: SystemdUnitFile,
+        quadlet_type: QuadletType,
+    ) -> Result<QuadletUnitFile, RuntimeError> {
+        let mut unit_file = unit_file;
+        normalize_key_case(&mut unit_file, &quadlet_type);
+
+        let conversion_error =
+            |e| RuntimeError::Conversion(format!("Converting {:?}", unit_file.path()), e);
+
+        if quadlet_type == QuadletType::Kube {
+            // drop-ins aren't merged yet, so they can still replace the Yaml= of the unit
+            convert::check_for_conflicting_yaml(&unit_file).map_err(conversion_error)?;
+        }
+
         let service_name = match quadlet_type {
-            QuadletType::Container => get_container_service_name(&unit_file).to_str().to_owned(),
-            QuadletType::Volume => get_volume_service_name(&unit_file).to_str().to_owned(),
-            QuadletType::Kube => get_kube_service_name(&unit_file).to_str().to_owned(),
-            QuadletType::Network => get_network_service_name(&unit_file).to_str().to_owned(),
-            QuadletType::Image => get_image_service_name(&unit_file).to_str().to_owned(),
-            QuadletType::Build => get_build_service_name(&unit_file).to_str().to_owned(),
-            QuadletType::Pod => get_pod_service_name(&unit_file).to_str().to_owned(),
-        };
+            QuadletType::Artifact => get_artifact_service_name(&unit_file),
+            QuadletType::Container => get_container_service_name(&unit_file),
+            QuadletType::Volume => get_volume_service_name(&unit_file),
+            QuadletType::Kube => get_kube_service_name(&unit_file),
+            QuadletType::Network => get_network_service_name(&unit_file),
+            QuadletType::Image => get_image_service_name(&unit_file),
+            QuadletType::Build => get_build_service_name(&unit_file),
+            QuadletType::Pod => get_pod_service_name(&unit_file),
+        }
+        .map_err(conversion_error)?
+        .to_str()
+        .to_owned();
         let resource_name = match quadlet_type {
             QuadletType::Build => {
                 // Prefill `resouce_name`s for .build files. This is significantly less complex than
@@ -175,7 +288,7 @@ impl QuadletUnitFile {
             }
             QuadletType::Container => {
                 // Prefill resouceNames for .container files. This solves network reusing.
-                get_container_resource_name(&unit_file)
+                get_container_resource_name(&unit_file).map_err(conversion_error)?
             }
             _ => String::default(),
         };
@@ -189,12 +302,78 @@ impl QuadletUnitFile {
         })
     }
 
+    // Has to be repeated after merging drop-ins, which may use a different case, too
+    pub(crate) fn normalize_key_case(&mut self) {
+        normalize_key_case(&mut self.unit_file, &self.quadlet_type);
+    }
+
     pub(crate) fn get_service_file_name(&self) -> OsString {
         PathBuf::from(format!("{}.service", self.service_name))
             .file_name()
             .expect("should have a file name")
             .to_os_string()
     }
+
+    /// Loads a unit of the given type from `data`, e.g. when read from stdin. The service name
+    /// is derived from `path` like for units loaded from files.
+    pub(crate) fn load_from_str(
+        path: &Path,
+        data: &str,
+        quadlet_type: QuadletType,
+    ) -> Result<QuadletUnitFile, RuntimeError> {
+        let unit_file = SystemdUnitFile::load_from_str(path, data).map_err(|e| {
+            RuntimeError::Conversion(
+                format!("Error loading {path:?}"),
+                ConversionError::Parsing(e),
+            )
+        })?;
+
+        QuadletUnitFile::with_type(unit_file, quadlet_type)
+    }
+
+    pub(crate) fn convert(
+        &self,
+        units_info_map: &mut UnitsInfoMap,
+        is_user: bool,
+    ) -> Result<SystemdUnitFile, ConversionError> {
+        let unit = &self.unit_file;
+        warn_if_ineffective_install_section(unit);
+        warn_if_unknown_sections(unit, &self.quadlet_type);
+        match self.quadlet_type {
+            QuadletType::Artifact => convert::from_artifact_unit(unit, units_info_map, is_user),
+            QuadletType::Build => convert::from_build_unit(unit, units_info_map, is_user),
+            QuadletType::Container => {
+                warn_if_ambiguous_image_name(unit, CONTAINER_SECTION);
+                convert::from_container_unit(unit, units_info_map, is_user)
+            }
+            QuadletType::Image => {
+                warn_if_ambiguous_image_name(unit, IMAGE_SECTION);
+                convert::from_image_unit(unit, units_info_map, is_user)
+            }
+            QuadletType::Kube => convert::from_kube_unit(unit, units_info_map, is_user),
+            QuadletType::Network => convert::from_network_unit(unit, units_info_map, is_user),
+            QuadletType::Pod => convert::from_pod_unit(unit, units_info_map, is_user),
+            QuadletType::Volume => {
+                warn_if_ambiguous_image_name(unit, VOLUME_SECTION);
+                convert::from_volume_unit(unit, units_info_map, is_user)
+            }
+        }
+    }
+
+    /// Checks the unit without converting it (e.g. for linting) and returns all errors found.
+    /// Errors depending on other units (e.g. a missing `Network=foo.network`) aren't detected.
+    pub(crate) fn validate(&self) -> Vec<ConversionError> {
+        convert::validate_unit(&self.unit_file, &self.quadlet_type)
+    }
+}
+
+// e.g. `image=` is taken as `Image=`
+fn normalize_key_case(unit_file: &mut SystemdUnitFile, quadlet_type: &QuadletType) {
+    unit_file.normalize_key_case(
+        quadlet_type.section_name(),
+        supported_keys(quadlet_type.clone()),
+    );
+    unit_file.normalize_key_case(QUADLET_SECTION, &SUPPORTED_QUADLET_KEYS);
 }
 
 #[derive(Debug, Default)]
@@ -214,22 +393,20 @@ impl UnitsInfoMap {
     }
 }
 
-fn get_build_service_name(build: &SystemdUnitFile) -> PathBuf {
+fn get_artifact_service_name(artifact: &SystemdUnitFile) -> Result<PathBuf, ConversionError> {
+    get_quadlet_service_name(artifact, ARTIFACT_SECTION, "-artifact")
+}
+
+fn get_build_service_name(build: &SystemdUnitFile) -> Result<PathBuf, ConversionError> {
     get_quadlet_service_name(build, BUILD_SECTION, "-build")
 }
 
 fn get_built_image_name(build: &SystemdUnitFile) -> Option<String> {
-    build
-        .lookup_all(BUILD_SECTION, "ImageTag")
+    // Tag= is an alias for ImageTag= (e.g. for users used to `docker build -t`)
+    ["ImageTag", "Tag"]
         .iter()
-        .filter_map(|s| {
-            if s.is_empty() {
-                None
-            } else {
-                Some(s.to_string())
-            }
-        })
-        .next()
+        .flat_map(|key| build.lookup_all(BUILD_SECTION, key))
+        .find(|s| !s.is_empty())
 }
 
 // Get the unresolved container name that may contain '%'.
@@ -249,60 +426,106 @@ fn get_container_name(container: &SystemdUnitFile) -> String {
 
 // Get the resolved container name that contains no '%'.
 // Returns an empty string if not resolvable.
-fn get_container_resource_name(container: &SystemdUnitFile) -> String {
+fn get_container_resource_name(container: &SystemdUnitFile) -> Result<String, ConversionError> {
     let container_name = get_container_name(container);
 
     // XXX: only %N is handled.
     // it is difficult to properly implement specifiers handling without consulting systemd.
     let resource_name =
-        container_name.replace("%N", get_container_service_name(container).to_str());
+        container_name.replace("%N", get_container_service_name(container)?.to_str());
 
     if !resource_name.contains("%") {
-        resource_name
+        Ok(resource_name)
     } else {
-        String::default()
+        Ok(String::default())
     }
 }
 
-fn get_container_service_name(container: &SystemdUnitFile) -> PathBuf {
+fn get_container_service_name(container: &SystemdUnitFile) -> Result<PathBuf, ConversionError> {
     get_quadlet_service_name(container, CONTAINER_SECTION, "")
 }
 
-fn get_image_service_name(image: &SystemdUnitFile) -> PathBuf {
+fn get_image_service_name(image: &SystemdUnitFile) -> Result<PathBuf, ConversionError> {
     get_quadlet_service_name(image, IMAGE_SECTION, "-image")
 }
 
-fn get_kube_service_name(kube: &SystemdUnitFile) -> PathBuf {
+fn get_kube_service_name(kube: &SystemdUnitFile) -> Result<PathBuf, ConversionError> {
     get_quadlet_service_name(kube, KUBE_SECTION, "")
 }
 
-fn get_network_service_name(network: &SystemdUnitFile) -> PathBuf {
+fn get_network_service_name(network: &SystemdUnitFile) -> Result<PathBuf, ConversionError> {
     get_quadlet_service_name(network, NETWORK_SECTION, "-network")
 }
 
-fn get_pod_service_name(pod: &SystemdUnitFile) -> PathBuf {
+fn get_pod_service_name(pod: &SystemdUnitFile) -> Result<PathBuf, ConversionError> {
     get_quadlet_service_name(pod, POD_SECTION, "-pod")
 }
 
-fn get_quadlet_service_name(unit: &SystemdUnitFile, section: &str, name_suffix: &str) -> PathBuf {
+fn get_quadlet_service_name(
+    unit: &SystemdUnitFile,
+    section: &str,
+    name_suffix: &str,
+) -> Result<PathBuf, ConversionError> {
     if let Some(service_name) = unit.lookup(section, "ServiceName") {
-        return PathBuf::from(service_name);
+        // the ".service" extension is added when generating the service file
+        let service_name = service_name
+            .strip_suffix(".service")
+            .unwrap_or(&service_name);
+        if service_name.is_empty() || service_name.contains('/') {
+            return Err(ConversionError::InvalidServiceName(service_name.into()));
+        }
+
+        return Ok(PathBuf::from(service_name));
     }
 
-    quad_replace_extension(
+    Ok(quad_replace_extension(
         Path::new(unit.path().file_name().unwrap()),
         "",
         "",
         name_suffix,
-    )
+    ))
 }
 
-fn get_volume_service_name(volume: &SystemdUnitFile) -> PathBuf {
+fn get_volume_service_name(volume: &SystemdUnitFile) -> Result<PathBuf, ConversionError> {
     get_quadlet_service_name(volume, VOLUME_SECTION, "-volume")
 }
 
 pub fn get_podman_binary() -> String {
-    env::var("PODMAN").unwrap_or(DEFAULT_PODMAN_BINARY.to_owned())
+    let podman = env::var("PODMAN").ok();
+
+    // unresolvable values are reported by `resolve_podman_binary()` before converting any units
+    resolve_podman_binary(podman.clone(), env::var_os("PATH"))
+        .unwrap_or_else(|_| podman.unwrap_or_default())
+}
+
+// systemd requires absolute paths for the commands it runs, so bare names (e.g. `PODMAN=podman`)
+// are looked up in `path`
+pub(crate) fn resolve_podman_binary(
+    podman: Option<String>,
+    path: Option<OsString>,
+) -> Result<String, RuntimeError> {
+    let Some(podman) = podman else {
+        return Ok(DEFAULT_PODMAN_BINARY.to_owned());
+    };
+
+    if podman.starts_with('/') {
+        return Ok(podman);
+    }
+    if podman.contains('/') {
+        return std::path::absolute(&podman)
+            .map(|p| p.to_str().to_owned())
+            .map_err(|e| RuntimeError::Io(format!("Can't resolve PODMAN={podman:?}"), e));
+    }
+
+    env::split_paths(&path.unwrap_or_default())
+        .map(|dir| dir.join(&podman))
+        .find(|candidate| {
+            candidate
+                .metadata()
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        })
+        .map(|p| p.to_str().to_owned())
+        .ok_or(RuntimeError::PodmanNotFound(podman))
 }
 
 fn is_image_id(image_name: &str) -> bool {
@@ -347,7 +570,7 @@ fn is_unambiguous_name(image_name: &str) -> bool {
 
 fn is_url(maybe_url: &str) -> bool {
     // this is a shortcut to keep binary size small, we don't need a full URL parser here
-    let re = Regex::new("^((https?)|(git)://)|(github\\.com/).+$").unwrap();
+    let re = Regex::new("^((https?|git)://|github\\.com/).+$").unwrap();
     re.is_match(maybe_url)
 }
 
@@ -374,6 +597,41 @@ pub(crate) fn warn_if_ambiguous_image_name(unit: &SystemdUnitFile, section: &str
     }
 }
 
+// The [Install] section is only acted upon when generating the service file.
+// Without any of the keys creating symlinks, it's silently ignored.
+pub(crate) fn warn_if_ineffective_install_section(unit: &SystemdUnitFile) {
+    if !unit.has_section(INSTALL_SECTION) {
+        return;
+    }
+
+    let is_effective = ["Alias", "WantedBy", "RequiredBy"]
+        .iter()
+        .any(|key| !unit.lookup_all_strv(INSTALL_SECTION, key).is_empty());
+    if !is_effective {
+        let file_name = unit.file_name();
+        warn!("{file_name:?} has an [Install] section without Alias=, WantedBy= or RequiredBy=, so it won't be enabled");
+    }
+}
+
+// All sections end up in the generated service file, where systemd ignores (and warns about) all
+// it doesn't know, except for the ones starting with `X-`.
+pub(crate) fn warn_if_unknown_sections(unit: &SystemdUnitFile, quadlet_type: &QuadletType) {
+    let known_sections = [
+        UNIT_SECTION,
+        SERVICE_SECTION,
+        INSTALL_SECTION,
+        QUADLET_SECTION,
+        quadlet_type.section_name(),
+    ];
+
+    for section in unit.section_names() {
+        if !known_sections.contains(&section) && !section.starts_with("X-") {
+            let file_name = unit.file_name();
+            warn!("{file_name:?} has an unknown [{section}] section, which will be ignored");
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -388,11 +646,47 @@ mod tests {
             unit_file.add(section, "ServiceName", "test-name");
 
             assert_eq!(
-                get_quadlet_service_name(&unit_file, section, "-test"),
+                get_quadlet_service_name(&unit_file, section, "-test").unwrap(),
                 PathBuf::from("test-name")
             )
         }
 
+        #[test]
+        fn strips_service_extension_from_service_name() {
+            let mut unit_file = SystemdUnitFile::new();
+            let section = "Foo";
+            unit_file.add(section, "ServiceName", "custom.service");
+
+            assert_eq!(
+                get_quadlet_service_name(&unit_file, section, "-test").unwrap(),
+                PathBuf::from("custom")
+            )
+        }
+
+        #[test]
+        fn fails_with_directory_in_service_name() {
+            let mut unit_file = SystemdUnitFile::new();
+            let section = "Foo";
+            unit_file.add(section, "ServiceName", "dir/custom");
+
+            assert!(matches!(
+                get_quadlet_service_name(&unit_file, section, "-test"),
+                Err(ConversionError::InvalidServiceName(name)) if name == "dir/custom"
+            ))
+        }
+
+        #[test]
+        fn fails_with_only_service_extension_in_service_name() {
+            let mut unit_file = SystemdUnitFile::new();
+            let section = "Foo";
+            unit_file.add(section, "ServiceName", ".service");
+
+            assert!(matches!(
+                get_quadlet_service_name(&unit_file, section, "-test"),
+                Err(ConversionError::InvalidServiceName(name)) if name.is_empty()
+            ))
+        }
+
         #[test]
         fn use_only_file_name_for_service_name() {
             let path = PathBuf::from("/foo/bar/baz.buf");
@@ -400,12 +694,321 @@ mod tests {
             unit_file.path = path;
 
             assert_eq!(
-                get_quadlet_service_name(&unit_file, "", "-test"),
+                get_quadlet_service_name(&unit_file, "", "-test").unwrap(),
                 PathBuf::from("baz-test")
             )
         }
     }
 
+    mod quadlet_type {
+        use super::*;
+
+        #[test]
+        fn from_extension_finds_supported_types() {
+            assert_eq!(
+                QuadletType::from_extension(OsStr::new("container")),
+                Some(QuadletType::Container)
+            );
+            assert_eq!(
+                QuadletType::from_extension(OsStr::new("volume")),
+                Some(QuadletType::Volume)
+            );
+        }
+
+        #[test]
+        fn from_extension_is_case_sensitive() {
+            assert_eq!(QuadletType::from_extension(OsStr::new("Container")), None);
+            assert_eq!(QuadletType::from_extension(OsStr::new("VOLUME")), None);
+        }
+
+        #[test]
+        fn from_path_agrees_with_from_extension() {
+            assert_eq!(
+                QuadletType::from_path(Path::new("/etc/foo.container")).unwrap(),
+                QuadletType::Container
+            );
+            for path in ["/etc/foo.Container", "/etc/foo.service", "/etc/foo"] {
+                assert!(matches!(
+                    QuadletType::from_path(Path::new(path)),
+                    Err(RuntimeError::UnsupportedQuadletType(p)) if p == Path::new(path)
+                ));
+            }
+        }
+    }
+
+    mod quadlet_unit_file {
+        use super::*;
+
+        #[test]
+        fn names_service_file_after_service_name() {
+            for service_name in ["custom", "custom.service"] {
+                let unit_file = SystemdUnitFile::load_from_str(
+                    Path::new("foo.container"),
+                    &format!("[Container]\nImage=foo\nServiceName={service_name}\n"),
+                )
+                .unwrap();
+
+                let quadlet = QuadletUnitFile::from_unit_file(unit_file).unwrap();
+
+                assert_eq!(quadlet.get_service_file_name(), "custom.service");
+            }
+        }
+
+        #[test]
+        fn elides_long_values_in_debug_output() {
+            let script = "echo foo\\n".repeat(1000);
+            let unit_file = SystemdUnitFile::load_from_str(
+                Path::new("foo.container"),
+                &format!("[Container]\nImage=foo\nExec=sh -c \"{script}\"\n"),
+            )
+            .unwrap();
+
+            let quadlet = QuadletUnitFile::from_unit_file(unit_file).unwrap();
+
+            let debug = format!("{quadlet:?}");
+            assert!(!debug.contains(&script), "{debug}");
+            assert!(debug.contains("... (10008 bytes))"), "{debug}");
+        }
+
+        #[test]
+        fn fails_with_directory_in_service_name() {
+            let unit_file = SystemdUnitFile::load_from_str(
+                Path::new("foo.container"),
+                "[Container]\nImage=foo\nServiceName=dir/custom\n",
+            )
+            .unwrap();
+
+            assert!(matches!(
+                QuadletUnitFile::from_unit_file(unit_file),
+                Err(RuntimeError::Conversion(_, ConversionError::InvalidServiceName(name))) if name == "dir/custom"
+            ));
+        }
+
+        #[test]
+        fn fails_with_conflicting_yaml() {
+            let result = QuadletUnitFile::load_from_str(
+                Path::new("foo.kube"),
+                "[Kube]\nYaml=/foo.yml\nyaml=/bar.yml\n",
+                QuadletType::Kube,
+            );
+
+            assert!(matches!(
+                result,
+                Err(RuntimeError::Conversion(_, ConversionError::ConflictingValuesForKey(key, values))) if key == "Yaml" && values == ["/foo.yml", "/bar.yml"]
+            ));
+        }
+
+        #[test]
+        fn validates_without_converting() {
+            let quadlet = QuadletUnitFile::load_from_str(
+                Path::new("foo.container"),
+                "[Container]\nFoo=bar\nNetwork=missing.network\n",
+                QuadletType::Container,
+            )
+            .unwrap();
+
+            let errors = quadlet.validate();
+
+            assert_eq!(errors.len(), 2, "{errors:?}");
+            assert!(
+                matches!(&errors[0], ConversionError::EmptyRequiredKey(key, _) if key == "Image or Rootfs")
+            );
+            assert!(matches!(&errors[1], ConversionError::UnknownKey(_)));
+        }
+
+        #[test]
+        fn accepts_keys_in_any_case() {
+            for key in ["image", "IMAGE", "iMaGe"] {
+                let quadlet = QuadletUnitFile::load_from_str(
+                    Path::new("foo.container"),
+                    &format!("[Container]\n{key}=localhost/foo\nservicename=custom\n"),
+                    QuadletType::Container,
+                )
+                .unwrap();
+
+                assert_eq!(
+                    quadlet.unit_file.lookup(CONTAINER_SECTION, "Image"),
+                    Some("localhost/foo".into())
+                );
+                assert_eq!(quadlet.get_service_file_name(), "custom.service");
+
+                let mut units_info_map = UnitsInfoMap::from_quadlet_units(vec![quadlet.clone()]);
+                let service = quadlet.convert(&mut units_info_map, false).unwrap();
+                assert!(service
+                    .lookup_last(systemd_unit::SERVICE_SECTION, "ExecStart")
+                    .unwrap()
+                    .ends_with(" localhost/foo"));
+            }
+        }
+    }
+
+    mod resolve_podman_binary {
+        use super::*;
+        use std::fs;
+
+        #[test]
+        fn defaults_to_usr_bin_podman() {
+            assert_eq!(
+                resolve_podman_binary(None, Some("/bin".into())).unwrap(),
+                "/usr/bin/podman"
+            );
+        }
+
+        #[test]
+        fn keeps_absolute_path() {
+            assert_eq!(
+                resolve_podman_binary(Some("/opt/podman/bin/podman".into()), None).unwrap(),
+                "/opt/podman/bin/podman"
+            );
+        }
+
+        #[test]
+        fn looks_up_bare_name_in_path() {
+            let empty_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let bin_dir = tempfile::tempdir().expect("cannot create temp dir");
+            // not executable, so it doesn't count
+            fs::write(empty_dir.path().join("podman"), "").unwrap();
+            fs::write(bin_dir.path().join("podman"), "#!/bin/sh\n").unwrap();
+            fs::set_permissions(
+                bin_dir.path().join("podman"),
+                fs::Permissions::from_mode(0o755),
+            )
+            .unwrap();
+            let path = env::join_paths([empty_dir.path(), bin_dir.path()]).unwrap();
+
+            assert_eq!(
+                resolve_podman_binary(Some("podman".into()), Some(path)).unwrap(),
+                bin_dir.path().join("podman").to_str()
+            );
+        }
+
+        #[test]
+        fn fails_with_bare_name_missing_from_path() {
+            let empty_dir = tempfile::tempdir().expect("cannot create temp dir");
+
+            assert!(matches!(
+                resolve_podman_binary(Some("podman".into()), Some(empty_dir.path().into())),
+                Err(RuntimeError::PodmanNotFound(name)) if name == "podman"
+            ));
+        }
+    }
+
+    mod supported_keys {
+        use super::*;
+
+        #[test]
+        fn returns_keys_checked_by_converters() {
+            use QuadletType::*;
+
+            for (typ, section, keys) in [
+                (Artifact, "Artifact", &SUPPORTED_ARTIFACT_KEYS[..]),
+                (Build, "Build", &SUPPORTED_BUILD_KEYS[..]),
+                (Container, "Container", &SUPPORTED_CONTAINER_KEYS[..]),
+                (Image, "Image", &SUPPORTED_IMAGE_KEYS[..]),
+                (Kube, "Kube", &SUPPORTED_KUBE_KEYS[..]),
+                (Network, "Network", &SUPPORTED_NETWORK_KEYS[..]),
+                (Pod, "Pod", &SUPPORTED_POD_KEYS[..]),
+                (Volume, "Volume", &SUPPORTED_VOLUME_KEYS[..]),
+            ] {
+                assert_eq!(typ.section_name(), section);
+                assert!(std::ptr::eq(supported_keys(typ), keys));
+            }
+        }
+    }
+
+    mod warn_if_ineffective_install_section {
+        use super::*;
+        use crate::quadlet::logger::test_logger::capture_logs;
+
+        fn warnings_for(data: &str) -> Vec<String> {
+            let unit_file =
+                SystemdUnitFile::load_from_str(Path::new("foo.container"), data).unwrap();
+
+            let (_, logs) = capture_logs(|| warn_if_ineffective_install_section(&unit_file));
+
+            logs.into_iter()
+                .filter(|(level, _)| *level == log::Level::Warn)
+                .map(|(_, message)| message)
+                .collect()
+        }
+
+        #[test]
+        fn warns_about_empty_install_section() {
+            assert_eq!(
+                warnings_for("[Container]\nImage=localhost/foo\n[Install]\n"),
+                vec!["\"foo.container\" has an [Install] section without Alias=, WantedBy= or RequiredBy=, so it won't be enabled"]
+            );
+            assert_eq!(
+                warnings_for("[Container]\nImage=localhost/foo\n[Install]\nWantedBy=\n"),
+                vec!["\"foo.container\" has an [Install] section without Alias=, WantedBy= or RequiredBy=, so it won't be enabled"]
+            );
+        }
+
+        #[test]
+        fn doesnt_warn_about_effective_install_section() {
+            for install in [
+                "WantedBy=multi-user.target",
+                "RequiredBy=foo.target",
+                "Alias=bar.service",
+            ] {
+                assert_eq!(
+                    warnings_for(&format!(
+                        "[Container]\nImage=localhost/foo\n[Install]\n{install}\n"
+                    )),
+                    Vec::<String>::new(),
+                    "{install}"
+                );
+            }
+        }
+
+        #[test]
+        fn doesnt_warn_without_install_section() {
+            assert_eq!(
+                warnings_for("[Container]\nImage=localhost/foo\n"),
+                Vec::<String>::new()
+            );
+        }
+    }
+
+    mod warn_if_unknown_sections {
+        use super::*;
+        use crate::quadlet::logger::test_logger::capture_logs;
+
+        fn warnings_for(data: &str) -> Vec<String> {
+            let unit_file =
+                SystemdUnitFile::load_from_str(Path::new("foo.container"), data).unwrap();
+
+            let (_, logs) =
+                capture_logs(|| warn_if_unknown_sections(&unit_file, &QuadletType::Container));
+
+            logs.into_iter()
+                .filter(|(level, _)| *level == log::Level::Warn)
+                .map(|(_, message)| message)
+                .collect()
+        }
+
+        #[test]
+        fn warns_about_unknown_sections() {
+            assert_eq!(
+                warnings_for("[Container]\nImage=localhost/foo\n[Socket]\n[Volume]\n[Socket]\n"),
+                vec![
+                    "\"foo.container\" has an unknown [Socket] section, which will be ignored",
+                    "\"foo.container\" has an unknown [Volume] section, which will be ignored",
+                ]
+            );
+        }
+
+        #[test]
+        fn doesnt_warn_about_known_sections() {
+            assert_eq!(
+                warnings_for(
+                    "[Unit]\n[Container]\nImage=localhost/foo\n[Service]\n[Install]\n[Quadlet]\n[X-Foo]\n"
+                ),
+                Vec::<String>::new()
+            );
+        }
+    }
+
     mod is_unambiguous_name {
         use super::*;
 
@@ -491,5 +1094,24 @@ mod tests {
         fn succeeds_with_git_schema() {
             assert!(is_url("git://github.com/riyad/quadlet-rs"))
         }
+
+        #[test]
+        fn succeeds_with_schemaless_github_url() {
+            assert!(is_url("github.com/org/repo"))
+        }
+
+        #[test]
+        fn fails_with_github_domain_not_at_start() {
+            assert!(!is_url("myrepo-github.com/x"));
+            assert!(!is_url("not-a-url-but-has-github.com/x"));
+            assert!(!is_url("./github.com/x"));
+        }
+
+        #[test]
+        fn fails_with_schema_without_separator() {
+            assert!(!is_url("https"));
+            assert!(!is_url("httpfoo/bar"));
+            assert!(!is_url("gitfoo://bar"));
+        }
     }
 }
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use crate::systemd_unit::quote_words;
 
 use super::get_podman_binary;
@@ -16,7 +14,7 @@ impl PodmanCommand {
         self.args.push(arg.into());
     }
 
-    pub(crate) fn add_annotations(&mut self, annotations: &HashMap<String, String>) {
+    pub(crate) fn add_annotations(&mut self, annotations: &[(String, String)]) {
         self.add_keys("--annotation", annotations);
     }
 
@@ -31,21 +29,27 @@ impl PodmanCommand {
         }
     }
 
-    pub(crate) fn add_env(&mut self, env: &HashMap<String, String>) {
+    pub(crate) fn add_env(&mut self, env: &[(String, String)]) {
         self.add_keys("--env", env);
     }
 
-    pub(crate) fn add_keys(&mut self, prefix: &str, env: &HashMap<String, String>) {
+    // Adds `prefix KEY=VALUE` for every pair, keeping their order
+    pub(crate) fn add_keys(&mut self, prefix: &str, env: &[(String, String)]) {
         for (key, value) in env {
             self.add(prefix);
             self.add(format!("{key}={value}"));
         }
     }
 
-    pub(crate) fn add_labels(&mut self, labels: &HashMap<String, String>) {
+    pub(crate) fn add_labels(&mut self, labels: &[(String, String)]) {
         self.add_keys("--label", labels);
     }
 
+    #[cfg(test)]
+    pub(crate) fn args(&self) -> &[String] {
+        &self.args
+    }
+
     pub(crate) fn add_slice(&mut self, args: &[&str]) {
         self.args.reserve(args.len());
         for arg in args {
@@ -71,3 +75,36 @@ impl PodmanCommand {
         quote_words(self.args.iter().map(|s| s.as_str()))
     }
 }
+
+// Splits an escaped command line (e.g. a generated `ExecStart=`) back into its arguments.
+// This is the inverse of `PodmanCommand::to_escaped_string()`, e.g. for making assertions in tests.
+#[cfg(test)]
+pub(crate) fn unescape_exec(value: &str) -> Vec<String> {
+    crate::systemd_unit::SplitWord::new(value).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod unescape_exec {
+        use super::*;
+
+        #[test]
+        fn splits_simple_words() {
+            assert_eq!(unescape_exec("podman run  --rm"), ["podman", "run", "--rm"]);
+        }
+
+        #[test]
+        fn round_trips_escaped_string() {
+            let mut podman = PodmanCommand::new();
+            podman.add_slice(&["run", "--name", "with space", "--env"]);
+            podman.add("QUOTES=\"double\" 'single'");
+            podman.add("sh -c 'echo foo; echo bar'");
+            podman.add("a;b");
+            podman.add("back\\slash");
+
+            assert_eq!(unescape_exec(&podman.to_escaped_string()), podman.args());
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::{QuadletType, RuntimeError};
+
+// Collects the results of a generator run, so they can be reported in a machine-readable form.
+#[derive(Debug, Default)]
+pub(crate) struct RunSummary {
+    // Number of source units by their type (i.e. extension)
+    counts: BTreeMap<&'static str, usize>,
+    generated_files: Vec<PathBuf>,
+    // Errors and the source unit files they occurred in (if known)
+    pub(crate) errors: Vec<(Option<PathBuf>, RuntimeError)>,
+}
+
+impl RunSummary {
+    pub(crate) fn add_error(&mut self, source: Option<&Path>, error: RuntimeError) {
+        self.errors.push((source.map(Path::to_path_buf), error));
+    }
+
+    pub(crate) fn add_generated_file(&mut self, path: &Path) {
+        self.generated_files.push(path.to_path_buf());
+    }
+
+    pub(crate) fn add_unit(&mut self, quadlet_type: &QuadletType) {
+        *self.counts.entry(quadlet_type.extension()).or_default() += 1;
+    }
+
+    pub(crate) fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        let counts: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(typ, count)| format!("{}: {count}", json_string(typ)))
+            .collect();
+        let generated_files: Vec<String> = self
+            .generated_files
+            .iter()
+            .map(|path| json_string(&path.to_string_lossy()))
+            .collect();
+        let errors: Vec<String> = self
+            .errors
+            .iter()
+            .map(|(source, error)| {
+                let source = source
+                    .as_ref()
+                    .map_or("null".to_string(), |s| json_string(&s.to_string_lossy()));
+                format!(
+                    "{{\"source\": {source}, \"message\": {}}}",
+                    json_string(&error.to_string())
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"counts\": {{{}}}, \"generated_files\": [{}], \"errors\": [{}]}}",
+            counts.join(", "),
+            generated_files.join(", "),
+            errors.join(", "),
+        )
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod json_string {
+        use super::*;
+
+        #[test]
+        fn quotes_plain_string() {
+            assert_eq!(json_string("foo bar"), "\"foo bar\"");
+        }
+
+        #[test]
+        fn escapes_special_chars() {
+            assert_eq!(
+                json_string("a\"b\\c\nd\te\u{1}"),
+                "\"a\\\"b\\\\c\\nd\\te\\u0001\""
+            );
+        }
+    }
+
+    mod run_summary {
+        use super::*;
+        use crate::quadlet::ConversionError;
+
+        #[test]
+        fn empty_report() {
+            let summary = RunSummary::default();
+
+            assert!(!summary.has_errors());
+            assert_eq!(
+                summary.to_json(),
+                "{\"counts\": {}, \"generated_files\": [], \"errors\": []}"
+            );
+        }
+
+        #[test]
+        fn report_for_mixed_run() {
+            let mut summary = RunSummary::default();
+
+            summary.add_unit(&QuadletType::Container);
+            summary.add_unit(&QuadletType::Volume);
+            summary.add_unit(&QuadletType::Container);
+            summary.add_generated_file(Path::new("/out/good.service"));
+            summary.add_generated_file(Path::new("/out/data-volume.service"));
+            summary.add_error(
+                Some(Path::new("/src/bad.container")),
+                RuntimeError::Conversion(
+                    "Converting \"/src/bad.container\"".into(),
+                    ConversionError::InvalidKillMode("foo".into()),
+                ),
+            );
+            summary.add_error(None, RuntimeError::UnsupportedUnitType("foo".into()));
+
+            assert!(summary.has_errors());
+            assert_eq!(
+                summary.to_json(),
+                concat!(
+                    "{\"counts\": {\"container\": 2, \"volume\": 1}, ",
+                    "\"generated_files\": [\"/out/good.service\", \"/out/data-volume.service\"], ",
+                    "\"errors\": [",
+                    "{\"source\": \"/src/bad.container\", \"message\": \"Converting \\\"/src/bad.container\\\": ",
+                    "invalid KillMode \\\"foo\\\": only \\\"mixed\\\" and \\\"control-group\\\" are supported, ",
+                    "with other modes container processes may outlive the service\"}, ",
+                    "{\"source\": null, \"message\": \"unsupported unit type \\\"foo\\\"\"}",
+                    "]}",
+                )
+            );
+        }
+    }
+}
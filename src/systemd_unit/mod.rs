@@ -15,6 +15,8 @@ pub use self::unit::*;
 pub use self::unit_file::*;
 pub(crate) use self::value::*;
 
+use std::path::PathBuf;
+
 // TODO: mimic https://doc.rust-lang.org/std/num/enum.IntErrorKind.html
 #[derive(Debug, PartialEq, thiserror::Error)]
 #[non_exhaustive]
@@ -25,6 +27,8 @@ pub enum Error {
     Unquoting(String),
     #[error("failed to parse unit file: {0}")]
     Unit(#[from] parser::ParseError),
+    #[error("failed to parse unit file {path}:{err}", path = .0.display(), err = .1)]
+    UnitFile(PathBuf, parser::ParseError),
 }
 
 pub(crate) fn parse_bool(s: &str) -> Result<bool, Error> {
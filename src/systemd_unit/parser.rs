@@ -673,7 +673,7 @@ mod tests {
             let mut parser = Parser::new(input);
             let old_pos = parser.column;
             assert_eq!(parser.parse_value(), Ok(input.into()),);
-            assert_eq!(parser.column, old_pos + 0);
+            assert_eq!(parser.column, old_pos);
         }
 
         #[test]
@@ -780,7 +780,7 @@ mod tests {
             let old_col = parser.column;
             assert_eq!(parser.parse_value(), Ok("text".into()),);
             assert_eq!(parser.line, old_line + 1);
-            assert_eq!(parser.column, old_col + 0);
+            assert_eq!(parser.column, old_col);
         }
 
         #[test]
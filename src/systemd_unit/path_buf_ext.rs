@@ -87,7 +87,7 @@ impl PathBufExt<PathBuf> for PathBuf {
             if template_instance.is_empty() {
                 (Some(template_base), None)
             } else {
-                (Some(template_base), Some(template_instance.into()))
+                (Some(template_base), Some(template_instance))
             }
         } else {
             (None, None)
@@ -407,7 +407,7 @@ mod tests {
                 let expected = PathBuf::from(input.1);
                 assert_eq!(
                     base_path.cleaned(),
-                    PathBuf::from(expected),
+                    expected,
                     "{base_path:?}"
                 );
             }
@@ -33,9 +33,9 @@ pub fn quote_value(value: &str) -> String {
             '\x0b' => escaped.push_str("\\v"),
             '\x0c' => escaped.push_str("\\f"),
             '\\'   => escaped.push_str("\\\\"),
-            ' '    => escaped.push_str(" "),
+            ' '    => escaped.push(' '),
             '"'    => escaped.push_str("\\\""),
-            '\''   => escaped.push_str("'"),
+            '\''   => escaped.push('\''),
             _ => escaped.push_str(&format!("\\x{:02x}", c as isize)[..]),
         }
     }
@@ -49,7 +49,8 @@ where
     words
         .map(|word| {
             let word = word.into();
-            if word_needs_escaping(word) {
+            // quotes keep empty words from vanishing
+            if word.is_empty() || word_needs_escaping(word) {
                 format!("\"{}\"", quote_value(word))
             } else {
                 word.to_string()
@@ -294,6 +295,13 @@ mod tests {
             )
         }
 
+        #[test]
+        fn adds_quotes_for_empty_words() {
+            let words = vec!["foo", "", "bar"];
+
+            assert_eq!(quote_words(words.into_iter()), "foo \"\" bar".to_string())
+        }
+
         #[test]
         fn joins_words_with_space() {
             let words = vec!["foo", "bar", "baz"];
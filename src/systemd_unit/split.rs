@@ -121,6 +121,7 @@ impl<'a> SplitWord<'a> {
         self.parse_until_none_of(separators);
 
         let mut quote: Option<char> = None; // None or Some('\'') or Some('"')
+        let mut quoted = false; // whether we've seen quotes, i.e. `""` is an (empty) word
         let mut backslash = false; // whether we've just seen a backslash
         while let Some(c) = self.c {
             if backslash {
@@ -143,7 +144,10 @@ impl<'a> SplitWord<'a> {
                 }
             } else {
                 match c {
-                    '\'' | '"' => quote = Some(c),
+                    '\'' | '"' => {
+                        quote = Some(c);
+                        quoted = true;
+                    }
                     '\\' => {
                         backslash = true;
                     }
@@ -163,7 +167,7 @@ impl<'a> SplitWord<'a> {
         //     // otherwise we'd have to push it onto `word`
         // }
 
-        if word.is_empty() {
+        if word.is_empty() && !quoted {
             None
         } else {
             Some(word)
@@ -286,7 +290,7 @@ impl<'a> SplitWord<'a> {
     }
 }
 
-impl<'a> Default for SplitWord<'_> {
+impl Default for SplitWord<'_> {
     fn default() -> Self {
         Self {
             chars: "".chars(),
@@ -295,7 +299,7 @@ impl<'a> Default for SplitWord<'_> {
     }
 }
 
-impl<'a> Iterator for SplitWord<'_> {
+impl Iterator for SplitWord<'_> {
     type Item = String;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -471,6 +475,18 @@ mod tests {
                 assert_eq!(split.next(), None);
             }
 
+            #[test]
+            fn some_for_empty_quoted_words() {
+                let input = "foo '' \"\" bar";
+
+                let mut split = SplitWord::new(input);
+                assert_eq!(split.next(), Some("foo".into()));
+                assert_eq!(split.next(), Some("".into()));
+                assert_eq!(split.next(), Some("".into()));
+                assert_eq!(split.next(), Some("bar".into()));
+                assert_eq!(split.next(), None);
+            }
+
             #[test]
             fn some_with_multiple_words() {
                 let input = "\tfoo bar\tbaz\r\n";
@@ -1,10 +1,10 @@
 use ordered_multimap::list_ordered_multimap::ListOrderedMultimap;
-use std::collections::HashMap;
+use std::fmt;
 use std::io;
 
-use super::{parser, Entries, EntryValue, SectionKey, SplitStrv, SplitWord};
+use super::{parser, Entries, EntryValue, Error, SectionKey, SplitStrv, SplitWord};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct SystemdUnit {
     pub(crate) sections: ListOrderedMultimap<SectionKey, Entries>,
 }
@@ -51,7 +51,7 @@ impl SystemdUnit {
     pub(crate) fn has_key(&self, section: &str, key: &str) -> bool {
         self.sections
             .get(section)
-            .map_or(false, |e| e.data.contains_key(key))
+            .is_some_and(|e| e.data.contains_key(key))
     }
 
     /// Retrun `true` if there's an (non-empty) instance of section `name`
@@ -60,6 +60,7 @@ impl SystemdUnit {
     }
 
     /// Number of unique sections (i.e. with different names)
+    #[allow(dead_code)]
     pub fn len(&self) -> usize {
         self.sections.keys_len()
     }
@@ -72,8 +73,13 @@ impl SystemdUnit {
         Ok(unit)
     }
 
-    /// Get an interator of values for all `key`s in all instances of `section`
-    pub(crate) fn lookup_all(&self, section: &str, key: &str) -> Vec<String> {
+    /// Get the (unquoted) values for all `key`s in all instances of `section` in the order they
+    /// were declared in.
+    ///
+    /// Like in Quadlet, an empty value (e.g. `Environment=`) drops all values before it, so
+    /// drop-ins can replace lists instead of only appending to them. Converters should use this
+    /// (or one of the other `lookup_all*()` methods) for keys that can be given multiple times.
+    pub fn lookup_all(&self, section: &str, key: &str) -> Vec<String> {
         self.lookup_all_values(section, key)
             .iter()
             .map(|v| v.unquote())
@@ -88,15 +94,20 @@ impl SystemdUnit {
     }
 
     /// Look up 'Environment' style key-value keys
-    pub(crate) fn lookup_all_key_val(&self, section: &str, key: &str) -> HashMap<String, String> {
+    /// The pairs are returned in the order they were declared in. Assigning a key again
+    /// overrides its value, but keeps its original position.
+    pub(crate) fn lookup_all_key_val(&self, section: &str, key: &str) -> Vec<(String, String)> {
         let all_key_vals = self.lookup_all_values(section, key);
 
-        let mut res = HashMap::with_capacity(all_key_vals.len());
+        let mut res: Vec<(String, String)> = Vec::with_capacity(all_key_vals.len());
 
         for key_vals in all_key_vals {
             for assigns in SplitWord::new(key_vals.raw().as_str()) {
                 if let Some((key, value)) = assigns.split_once('=') {
-                    res.insert(key.to_string(), value.to_string());
+                    match res.iter_mut().find(|(k, _)| k == key) {
+                        Some((_, v)) => *v = value.to_string(),
+                        None => res.push((key.to_string(), value.to_string())),
+                    }
                 }
             }
         }
@@ -150,6 +161,17 @@ impl SystemdUnit {
             .map(|v| v.to_bool().unwrap_or(false))
     }
 
+    // Like `lookup_bool()`, but fails for invalid values instead of treating them as `false`
+    pub(crate) fn lookup_bool_strict(
+        &self,
+        section: &str,
+        key: &str,
+    ) -> Result<Option<bool>, Error> {
+        self.lookup_last_value(section, key)
+            .map(|v| v.to_bool())
+            .transpose()
+    }
+
     //TODO: lookup_int() == lookup_i64()
     //TODO: lookup_u32()
     //TODO: lookup_uid()
@@ -169,7 +191,7 @@ impl SystemdUnit {
             .unwrap_or_default()
             .data
             .get_all(key)
-            .last()
+            .next_back()
     }
 
     pub(crate) fn new() -> Self {
@@ -186,6 +208,28 @@ impl SystemdUnit {
         }
     }
 
+    /// Renames keys in `section` which only differ in case from one of `keys` to the latter.
+    /// All entries keep their values and their order.
+    pub(crate) fn normalize_key_case(&mut self, section: &str, keys: &[&str]) {
+        let Some(entries) = self.sections.get_mut(section) else {
+            return;
+        };
+
+        let canonical_key = |key: &str| {
+            keys.iter()
+                .find(|k| **k != key && k.eq_ignore_ascii_case(key))
+                .copied()
+        };
+        if !entries.data.keys().any(|k| canonical_key(k).is_some()) {
+            return;
+        }
+
+        for (key, value) in std::mem::take(&mut entries.data) {
+            let key = canonical_key(&key).map_or(key, str::to_string);
+            entries.data.append(key, value);
+        }
+    }
+
     /// Prepends `key=value` to last instance of `section`
     pub(crate) fn prepend<S, K>(&mut self, section: S, key: K, value: &str)
     where
@@ -199,7 +243,7 @@ impl SystemdUnit {
     fn prepend_entry_value(&mut self, section: String, key: String, value: EntryValue) {
         let old_values: Vec<_> = self.sections.remove_all(&section).collect();
 
-        self.add_entry_value(section.clone(), key.into(), value);
+        self.add_entry_value(section.clone(), key, value);
 
         for entries in old_values {
             for (ek, ev) in entries.data {
@@ -208,6 +252,22 @@ impl SystemdUnit {
         }
     }
 
+    /// Removes the last ocurrence of `key` in `section`, keeping all other entries in order
+    pub(crate) fn remove_last_entry(&mut self, section: &str, key: &str) {
+        if let Some(entries) = self.sections.get_mut(section) {
+            let count = entries.data.entry_len(key);
+            let mut seen = 0;
+
+            entries.data.retain(|k, _| {
+                if k != key {
+                    return true;
+                }
+                seen += 1;
+                seen < count
+            });
+        }
+    }
+
     pub(crate) fn rename_section<S: Into<String>>(&mut self, from: S, to: S) {
         let from_key = from.into();
 
@@ -229,6 +289,12 @@ impl SystemdUnit {
         }
     }
 
+    /// Names of all sections (including empty ones), each only once, in the order they first
+    /// appear in
+    pub fn section_names(&self) -> impl Iterator<Item = &str> {
+        self.sections.keys().map(String::as_str)
+    }
+
     pub(crate) fn section_entries<S: Into<String>>(
         &self,
         name: S,
@@ -259,6 +325,7 @@ impl SystemdUnit {
     }
 
     /// Updates the last ocurrence of key to value
+    #[allow(dead_code)]
     pub(crate) fn set_raw<S, K>(
         &mut self,
         section: S,
@@ -275,21 +342,21 @@ impl SystemdUnit {
     }
 
     fn set_entry_value(&mut self, section: String, key: String, value: EntryValue) {
-        let entries = self.sections.entry(section).or_insert(Entries::default());
+        // replace the "old" last value by appending a "new" one, all other values keep their order
+        self.remove_last_entry(&section, &key);
 
-        // we can't replace the last value directly, so we have to get "creative" O.o
-        // we do a stupid form of read-modify-write called remove-modify-append m(
-        // the good thing is: both remove() and append preserve the order of values (with this key)
-        let mut values: Vec<_> = entries.data.remove_all(&key).collect();
-        values.pop(); // remove the "old" last value ...
+        self.sections
+            .entry(section)
+            .or_insert(Entries::default())
+            .data
+            .append(key, value);
+    }
 
-        // ... reinsert all the values again ...
-        for v in values {
-            entries.data.append(key.clone(), v);
+    /// Removes all entries with `key` in `section`
+    pub(crate) fn unset(&mut self, section: &str, key: &str) {
+        if let Some(entries) = self.sections.get_mut(section) {
+            entries.data.remove_all(key);
         }
-
-        // ... and append a "new" last value
-        entries.data.append(key, value);
     }
 
     /// Write to a writer
@@ -306,32 +373,17 @@ impl SystemdUnit {
     }
 }
 
-impl Default for SystemdUnit {
-    fn default() -> Self {
-        Self {
-            sections: Default::default(),
-        }
-    }
-}
-
-impl ToString for SystemdUnit {
-    fn to_string(&self) -> String {
-        let mut res = String::new();
-
+impl fmt::Display for SystemdUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for (section, entries) in &self.sections {
-            res.push('[');
-            res.push_str(section);
-            res.push_str("]\n");
+            writeln!(f, "[{section}]")?;
             for (k, v) in &entries.data {
-                res.push_str(k);
-                res.push('=');
-                res.push_str(v.raw());
-                res.push('\n');
+                writeln!(f, "{k}={}", v.raw())?;
             }
-            res.push('\n');
+            writeln!(f)?;
         }
 
-        res
+        Ok(())
     }
 }
 
@@ -955,11 +1007,66 @@ Key1=valA2.3";
                 let values: Vec<_> = unit.lookup_all("secA", "Key1");
                 assert_eq!(values, vec!["valA2.2", "valA2.3"],);
             }
+
+            #[test]
+            fn finds_nothing_when_reset_last() {
+                let input = "[secA]
+Key1=valA1.1
+[secA]
+Key1=valA2.1
+Key1=";
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert_eq!(unit.lookup_all("secA", "Key1"), Vec::<String>::new());
+            }
+
+            #[test]
+            fn reset_only_affects_its_key_and_section() {
+                let input = "[secA]
+Key1=valA1
+Key2=valA2
+[secB]
+Key1=valB1
+[secA]
+Key1=";
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert_eq!(unit.lookup_all("secA", "Key1"), Vec::<String>::new());
+                assert_eq!(unit.lookup_all("secA", "Key2"), vec!["valA2"]);
+                assert_eq!(unit.lookup_all("secB", "Key1"), vec!["valB1"]);
+            }
+
+            #[test]
+            fn finds_values_readded_after_reset_in_merged_units() {
+                let mut unit = SystemdUnit::load_from_str(
+                    "[secA]
+Key1=base1
+Key1=base2",
+                )
+                .unwrap();
+                let first = SystemdUnit::load_from_str(
+                    "[secA]
+Key1=
+Key1=first",
+                )
+                .unwrap();
+                let second = SystemdUnit::load_from_str(
+                    "[secA]
+Key1=second",
+                )
+                .unwrap();
+
+                unit.merge_from(&first);
+                assert_eq!(unit.lookup_all("secA", "Key1"), vec!["first"]);
+
+                unit.merge_from(&second);
+                assert_eq!(unit.lookup_all("secA", "Key1"), vec!["first", "second"]);
+            }
         }
 
         mod lookup_all_args {
-            use super::*;
-
             #[test]
             #[ignore]
             fn todo() {
@@ -971,15 +1078,86 @@ Key1=valA2.3";
             use super::*;
 
             #[test]
-            #[ignore]
-            fn todo() {
-                todo!()
+            fn keeps_declaration_order() {
+                let input = "[Container]
+Environment=C=3 A=1
+Environment=B=2
+Environment=D=4";
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert_eq!(
+                    unit.lookup_all_key_val("Container", "Environment"),
+                    vec![
+                        ("C".into(), "3".into()),
+                        ("A".into(), "1".into()),
+                        ("B".into(), "2".into()),
+                        ("D".into(), "4".into()),
+                    ]
+                );
+            }
+
+            #[test]
+            fn later_assignment_overrides_value_in_place() {
+                let input = "[Container]
+Environment=A=1 B=2
+Environment=A=3";
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert_eq!(
+                    unit.lookup_all_key_val("Container", "Environment"),
+                    vec![("A".into(), "3".into()), ("B".into(), "2".into())]
+                );
+            }
+
+            #[test]
+            fn keeps_quoted_values_with_spaces() {
+                let input = r#"[Container]
+Environment="GREETING=hello world" FAREWELL="good bye" 'SINGLE=a  b'"#;
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert_eq!(
+                    unit.lookup_all_key_val("Container", "Environment"),
+                    vec![
+                        ("GREETING".into(), "hello world".into()),
+                        ("FAREWELL".into(), "good bye".into()),
+                        ("SINGLE".into(), "a  b".into()),
+                    ]
+                );
             }
         }
 
         mod lookup_all_strv {
             use super::*;
 
+            #[test]
+            fn splits_values() {
+                let input = "[secA]
+Key1=a b
+Key1=c";
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert_eq!(unit.lookup_all_strv("secA", "Key1"), vec!["a", "b", "c"]);
+            }
+
+            #[test]
+            fn empty_value_resets_list() {
+                let input = "[secA]
+Key1=a b
+Key1=c
+[secA]
+Key1=";
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert!(unit.lookup_all_strv("secA", "Key1").is_empty());
+            }
+        }
+
+        mod lookup_bool {
             #[test]
             #[ignore]
             fn todo() {
@@ -987,13 +1165,37 @@ Key1=valA2.3";
             }
         }
 
-        mod lookup_bool {
+        mod lookup_bool_strict {
             use super::*;
 
             #[test]
-            #[ignore]
-            fn todo() {
-                todo!()
+            fn parses_valid_values() {
+                let input = "[secA]
+Key1=yes
+Key2=off
+Key3=
+Key1=true";
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert_eq!(unit.lookup_bool_strict("secA", "Key1"), Ok(Some(true)));
+                assert_eq!(unit.lookup_bool_strict("secA", "Key2"), Ok(Some(false)));
+                assert_eq!(unit.lookup_bool_strict("secA", "Key3"), Ok(Some(false)));
+                assert_eq!(unit.lookup_bool_strict("secA", "Key4"), Ok(None));
+            }
+
+            #[test]
+            fn fails_with_invalid_value() {
+                let input = "[secA]
+Key1=maybe";
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert_eq!(
+                    unit.lookup_bool_strict("secA", "Key1"),
+                    Err(Error::ParseBool)
+                );
+                assert_eq!(unit.lookup_bool("secA", "Key1"), Some(false));
             }
         }
 
@@ -1031,6 +1233,27 @@ Key2=valA2";
         mod merge_from {
             use super::super::SystemdUnit;
 
+            #[test]
+            fn merged_empty_value_resets_list_keys() {
+                let input_to = "[Container]
+Environment=A=1 B=2
+Environment=C=3";
+                let input_from = "[Container]
+Environment=
+Environment=D=4";
+
+                let mut unit_to = SystemdUnit::load_from_str(input_to).unwrap();
+                let unit_from = SystemdUnit::load_from_str(input_from).unwrap();
+
+                unit_to.merge_from(&unit_from);
+
+                assert_eq!(
+                    unit_to.lookup_all_key_val("Container", "Environment"),
+                    vec![("D".into(), "4".into())]
+                );
+                assert_eq!(unit_to.lookup_all("Container", "Environment"), vec!["D=4"]);
+            }
+
             #[test]
             fn merging_non_overlapping_section_succeeds() {
                 let input_to = "[Section A]
@@ -1114,6 +1337,46 @@ KeyThree=value a3.from";
             }
         }
 
+        mod normalize_key_case {
+            use super::*;
+
+            #[test]
+            fn renames_keys_differing_in_case_in_place() {
+                let input = "[Section A]
+keyone=value 1
+KeyTwo=value 2
+KEYONE=value 3
+Unknown=value 4";
+
+                let mut unit = SystemdUnit::load_from_str(input).unwrap();
+
+                unit.normalize_key_case("Section A", &["KeyOne", "KeyTwo"]);
+
+                let mut iter = unit.section_entries("Section A");
+                assert_eq!(iter.next(), Some(("KeyOne", "value 1".into())));
+                assert_eq!(iter.next(), Some(("KeyTwo", "value 2".into())));
+                assert_eq!(iter.next(), Some(("KeyOne", "value 3".into())));
+                assert_eq!(iter.next(), Some(("Unknown", "value 4".into())));
+                assert_eq!(iter.next(), None);
+            }
+
+            #[test]
+            fn ignores_other_sections() {
+                let input = "[Section A]
+keyone=value 1
+[Section B]
+keyone=value 2";
+
+                let mut unit = SystemdUnit::load_from_str(input).unwrap();
+
+                unit.normalize_key_case("Section B", &["KeyOne"]);
+
+                assert!(unit.has_key("Section A", "keyone"));
+                assert!(unit.has_key("Section B", "KeyOne"));
+                assert!(!unit.has_key("Section B", "keyone"));
+            }
+        }
+
         mod prepend {
             use super::*;
 
@@ -1183,6 +1446,50 @@ KeyOne=value 2.1";
             }
         }
 
+        mod remove_last_entry {
+            use super::*;
+
+            #[test]
+            fn removes_only_last_entry_with_key() {
+                let input = "[Section A]
+KeyOne=value 1
+KeyTwo=value 2
+KeyOne=value 3
+KeyThree=value 4
+KeyOne=value 5";
+
+                let mut unit = SystemdUnit::load_from_str(input).unwrap();
+
+                unit.remove_last_entry("Section A", "KeyOne");
+
+                let mut iter = unit.section_entries("Section A");
+                assert_eq!(iter.next(), Some(("KeyOne", "value 1".into())));
+                assert_eq!(iter.next(), Some(("KeyTwo", "value 2".into())));
+                assert_eq!(iter.next(), Some(("KeyOne", "value 3".into())));
+                assert_eq!(iter.next(), Some(("KeyThree", "value 4".into())));
+                assert_eq!(iter.next(), None);
+                drop(iter);
+
+                unit.remove_last_entry("Section A", "KeyOne");
+
+                assert_eq!(unit.lookup_all("Section A", "KeyOne"), vec!["value 1"]);
+                assert_eq!(unit.lookup("Section A", "KeyTwo"), Some("value 2".into()));
+            }
+
+            #[test]
+            fn ignores_unknown_section_and_key() {
+                let input = "[Section A]
+KeyOne=value 1";
+
+                let mut unit = SystemdUnit::load_from_str(input).unwrap();
+
+                unit.remove_last_entry("Section B", "KeyOne");
+                unit.remove_last_entry("Section A", "KeyTwo");
+
+                assert_eq!(unit.lookup_all("Section A", "KeyOne"), vec!["value 1"]);
+            }
+        }
+
         mod rename_section {
             use super::*;
 
@@ -1296,6 +1603,51 @@ KeyThree=value 3";
             }
         }
 
+        mod section_names {
+            use super::*;
+
+            #[test]
+            fn with_empty_file() {
+                let unit = SystemdUnit::load_from_str("").unwrap();
+
+                assert_eq!(unit.section_names().count(), 0);
+            }
+
+            #[test]
+            fn with_repeated_and_interleaved_sections() {
+                let input = "[Section B]
+KeyOne=value 1
+[Section A]
+KeyOne=value 2
+[Section B]
+KeyTwo=value 3
+[Section C]
+[Section A]
+[section a]
+KeyOne=value 4";
+
+                let unit = SystemdUnit::load_from_str(input).unwrap();
+
+                assert_eq!(
+                    unit.section_names().collect::<Vec<_>>(),
+                    vec!["Section B", "Section A", "Section C", "section a"]
+                );
+                assert_eq!(unit.section_names().count(), unit.len());
+            }
+
+            #[test]
+            fn includes_sections_added_later() {
+                let mut unit = SystemdUnit::load_from_str("[Section A]\nKeyOne=value 1").unwrap();
+                unit.add("Section B", "KeyOne", "value 2");
+                unit.add("Section A", "KeyTwo", "value 3");
+
+                assert_eq!(
+                    unit.section_names().collect::<Vec<_>>(),
+                    vec!["Section A", "Section B"]
+                );
+            }
+        }
+
         mod section_entries {
             use super::*;
 
@@ -1479,7 +1831,7 @@ ExecStart=/some/path \"an arg\" \"a;b\\nc\\td\'e\" a;b\\nc\\td \'a\"b\'";
 
                 let mut command = PodmanCommand::new();
                 command.add("test");
-                command.extend(split_words.into_iter());
+                command.extend(split_words);
 
                 let new_exec_start = command.to_escaped_string();
                 assert_eq!(
@@ -1556,5 +1908,37 @@ KeyThree=\"value 3\"
                 );
             }
         }
+
+        mod unset {
+            use super::*;
+
+            #[test]
+            fn removes_all_entries_with_key() {
+                let input = "[Section A]
+KeyOne=value 1
+KeyTwo=value 2
+KeyOne=value 3";
+
+                let mut unit = SystemdUnit::load_from_str(input).unwrap();
+
+                unit.unset("Section A", "KeyOne");
+
+                let mut iter = unit.section_entries("Section A");
+                assert_eq!(iter.next(), Some(("KeyTwo", "value 2".into())));
+                assert_eq!(iter.next(), None);
+            }
+
+            #[test]
+            fn ignores_unknown_section() {
+                let input = "[Section A]
+KeyOne=value 1";
+
+                let mut unit = SystemdUnit::load_from_str(input).unwrap();
+
+                unit.unset("Section B", "KeyOne");
+
+                assert!(unit.has_key("Section A", "KeyOne"));
+            }
+        }
     }
 }
@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::ffi::OsString;
 use std::fs;
 use std::io;
+#[cfg(feature = "gzip")]
+use std::io::Read;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 
@@ -20,21 +22,26 @@ pub enum IoError {
     Unit(#[from] super::Error),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl IoError {
+    /// The kind of the underlying I/O error, `None` for parse errors.
+    pub fn kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            IoError::Io(e) => Some(e.kind()),
+            IoError::Unit(_) => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.kind() == Some(io::ErrorKind::NotFound)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct SystemdUnitFile {
     pub(crate) path: PathBuf,
     unit: SystemdUnit,
 }
 
-impl Default for SystemdUnitFile {
-    fn default() -> Self {
-        Self {
-            path: Default::default(),
-            unit: Default::default(),
-        }
-    }
-}
-
 impl Deref for SystemdUnitFile {
     type Target = SystemdUnit;
 
@@ -55,31 +62,61 @@ impl SystemdUnitFile {
     }
 
     pub fn is_template_unit(&self) -> bool {
-        match self.path().file_name_template_parts() {
-            (Some(_), _) => true,
-            _ => false,
-        }
+        matches!(self.path().file_name_template_parts(), (Some(_), _))
     }
 
+    /// Loads the unit file at `path`. With the "gzip" feature, compressed unit files
+    /// (e.g. `foo.container.gz`) are decompressed and loaded as if they weren't compressed
+    /// (i.e. as `foo.container`).
     pub fn load_from_path(path: &Path) -> Result<Self, IoError> {
-        let buf = fs::read_to_string(&path)?;
+        #[cfg(feature = "gzip")]
+        if is_compressed(path) {
+            let mut buf = String::new();
+            flate2::read::GzDecoder::new(fs::File::open(path)?).read_to_string(&mut buf)?;
+
+            let mut unit_file = SystemdUnitFile::load_from_str(path, buf.as_str())?;
+            unit_file.path = uncompressed_path(path);
+
+            return Ok(unit_file);
+        }
+
+        let buf = fs::read_to_string(path)?;
+
+        Ok(SystemdUnitFile::load_from_str(path, buf.as_str())?)
+    }
+
+    // Parses `data` as if it had been read from a file at `path`.
+    // Parse errors will mention `path`.
+    pub fn load_from_str(path: &Path, data: &str) -> Result<Self, super::Error> {
+        let unit = SystemdUnit::load_from_str(data).map_err(|e| match e {
+            super::Error::Unit(e) => super::Error::UnitFile(path.into(), e),
+            e => e,
+        })?;
 
         Ok(SystemdUnitFile {
             path: path.into(),
-            unit: SystemdUnit::load_from_str(buf.as_str())?,
+            unit,
         })
     }
 
+    /// Merges drop-ins into the unit. Type-wide drop-ins (e.g. from `container.d/`) are
+    /// merged first, then the ones for this unit (e.g. from `foo.container.d/`), so
+    /// the latter take precedence over the former and both over the unit file itself.
+    /// A unit's drop-in masks a type-wide drop-in with the same file name. Within each group
+    /// drop-ins are merged in lexical order of their file names across all `source_paths`,
+    /// with a drop-in from an earlier (i.e. higher priority) source path masking those with
+    /// the same file name from later ones.
+    /// Returns the paths of the merged drop-ins in the order they were merged in.
     pub fn load_dropins_from<'i, I: IntoIterator<Item = &'i Path>>(
         self: &mut SystemdUnitFile,
         source_paths: I,
-    ) -> Result<(), IoError> {
+    ) -> Result<Vec<PathBuf>, IoError> {
         let source_paths = Vec::from_iter(source_paths);
 
         let mut dropin_dirs: Vec<PathBuf> = Vec::new();
 
         for source_path in &source_paths {
-            let mut unit_dropin_dir = self.path().as_os_str().to_os_string();
+            let mut unit_dropin_dir = self.file_name().to_os_string();
             unit_dropin_dir.push(".d");
             dropin_dirs.push(source_path.join(unit_dropin_dir));
         }
@@ -87,59 +124,32 @@ impl SystemdUnitFile {
         // For instantiated templates, also look in the non-instanced template dropin dirs
         if let (Some(template_base), Some(_)) = self.path().file_name_template_parts() {
             for source_path in &source_paths {
-                let template_dropin_dir = self
-                    .path()
-                    .with_file_name(format!("{template_base}@.{}.d", self.unit_type()));
+                let template_dropin_dir = format!("{template_base}@.{}.d", self.unit_type());
                 dropin_dirs.push(source_path.join(template_dropin_dir));
             }
         }
 
-        let mut dropin_paths: HashMap<OsString, PathBuf> = HashMap::new();
-        for dropin_dir in dropin_dirs {
-            for entry in WalkDir::new(&dropin_dir) {
-                let dropin_file = match entry {
-                    Ok(entry) => entry,
-                    Err(e) => {
-                        if let Some(io_error) = e.io_error() {
-                            match io_error.kind() {
-                                io::ErrorKind::NotFound => {} // ignore missing drop-in directories
-                                _ => {
-                                    return Err(IoError::Io(
-                                        //format!("error reading directory {dropin_dir:?}"),
-                                        e.into(),
-                                    ));
-                                }
-                            }
-                        }
-                        continue;
-                    }
-                };
+        let type_dropin_dirs: Vec<PathBuf> = source_paths
+            .iter()
+            .map(|source_path| source_path.join(format!("{}.d", self.unit_type())))
+            .collect();
 
-                let dropin_name = dropin_file.file_name();
-                if dropin_file.path().extension().unwrap_or_default() != "conf" {
-                    // Only *.conf supported
-                    continue;
-                }
+        let dropin_paths = collect_dropins(dropin_dirs)?;
+        let mut type_dropin_paths = collect_dropins(type_dropin_dirs)?;
+        type_dropin_paths.retain(|dropin_name, _| !dropin_paths.contains_key(dropin_name));
 
-                if dropin_paths.contains_key(dropin_name) {
-                    // We already saw this name
-                    continue;
-                }
+        let mut merged_paths = self.merge_dropins(type_dropin_paths)?;
+        merged_paths.extend(self.merge_dropins(dropin_paths)?);
 
-                dropin_paths.insert(dropin_name.to_owned(), dropin_dir.join(dropin_name));
-            }
-        }
-
-        let mut dropin_files: Vec<&OsString> = dropin_paths.keys().collect();
+        Ok(merged_paths)
+    }
 
+    fn merge_dropins(
+        &mut self,
+        dropin_paths: BTreeMap<OsString, PathBuf>,
+    ) -> Result<Vec<PathBuf>, IoError> {
         // Merge in alpha-numerical order
-        dropin_files.sort_unstable();
-
-        for dropin_file in dropin_files {
-            let dropin_path = dropin_paths
-                .get(dropin_file.as_os_str())
-                .expect("drop-in should be there");
-
+        for dropin_path in dropin_paths.values() {
             debug!("Loading source drop-in file {dropin_path:?}");
 
             match SystemdUnitFile::load_from_path(dropin_path) {
@@ -153,7 +163,7 @@ impl SystemdUnitFile {
             }
         }
 
-        Ok(())
+        Ok(dropin_paths.into_values().collect())
     }
 
     pub fn new() -> Self {
@@ -176,6 +186,63 @@ impl SystemdUnitFile {
     }
 }
 
+// Compressed unit files are only supported with the "gzip" feature
+fn is_compressed(path: &Path) -> bool {
+    cfg!(feature = "gzip") && path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// The path a unit file is loaded as, e.g. `foo.container` for a compressed `foo.container.gz`
+pub fn uncompressed_path(path: &Path) -> PathBuf {
+    if is_compressed(path) {
+        path.with_extension("")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+// Collects `*.conf` files from all `dropin_dirs`, sorted by their file names. The dirs are
+// expected to be ordered by priority (highest first), so a drop-in masks all drop-ins with
+// the same file name in the dirs after it.
+fn collect_dropins(dropin_dirs: Vec<PathBuf>) -> Result<BTreeMap<OsString, PathBuf>, IoError> {
+    let mut dropin_paths: BTreeMap<OsString, PathBuf> = BTreeMap::new();
+    // go from lowest to highest priority, so higher priority dirs overwrite (i.e. mask) earlier entries
+    for dropin_dir in dropin_dirs.iter().rev() {
+        for entry in WalkDir::new(dropin_dir).min_depth(1).max_depth(1) {
+            let dropin_file = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    if let Some(io_error) = e.io_error() {
+                        match io_error.kind() {
+                            io::ErrorKind::NotFound => {} // ignore missing drop-in directories
+                            _ => {
+                                return Err(IoError::Io(
+                                    //format!("error reading directory {dropin_dir:?}"),
+                                    e.into(),
+                                ));
+                            }
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            if dropin_file.file_type().is_dir() {
+                continue;
+            }
+
+            let dropin_name = dropin_file.file_name();
+            if dropin_file.path().extension().unwrap_or_default() != "conf" {
+                // Only *.conf supported
+                continue;
+            }
+
+            dropin_paths.insert(dropin_name.to_owned(), dropin_dir.join(dropin_name));
+        }
+    }
+
+    Ok(dropin_paths)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -271,6 +338,358 @@ mod tests {
         }
     }
 
+    mod load_dropins_from {
+        use super::*;
+
+        fn write(path: PathBuf, data: &str) -> PathBuf {
+            fs::create_dir_all(path.parent().unwrap()).expect("cannot create dir");
+            fs::write(&path, data).expect("cannot write file");
+            path
+        }
+
+        #[test]
+        fn applies_type_wide_dropins_to_all_units_of_that_type() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let dir = temp_dir.path();
+            let a = write(dir.join("a.container"), "[Container]\nImage=a\n");
+            let b = write(dir.join("b.container"), "[Container]\nImage=b\n");
+            let c = write(dir.join("c.volume"), "[Volume]\n");
+            write(
+                dir.join("container.d/10-common.conf"),
+                "[Container]\nLabel=common\n",
+            );
+
+            for path in [a, b] {
+                let mut unit_file = SystemdUnitFile::load_from_path(&path).unwrap();
+                unit_file.load_dropins_from([dir]).unwrap();
+
+                assert_eq!(unit_file.lookup_all("Container", "Label"), vec!["common"]);
+            }
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&c).unwrap();
+            unit_file.load_dropins_from([dir]).unwrap();
+            assert!(!unit_file.has_key("Container", "Label"));
+        }
+
+        #[test]
+        fn unit_dropins_take_precedence_over_type_wide_dropins() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let dir = temp_dir.path();
+            let a = write(dir.join("a.container"), "[Container]\nImage=main\n");
+            write(
+                dir.join("container.d/20-image.conf"),
+                "[Container]\nImage=type\n",
+            );
+            write(
+                dir.join("a.container.d/10-image.conf"),
+                "[Container]\nImage=unit\n",
+            );
+            write(
+                dir.join("container.d/30-same.conf"),
+                "[Container]\nLabel=type\n",
+            );
+            write(
+                dir.join("a.container.d/30-same.conf"),
+                "[Container]\nLabel=unit\n",
+            );
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&a).unwrap();
+            unit_file.load_dropins_from([dir]).unwrap();
+
+            assert_eq!(
+                unit_file.lookup_last("Container", "Image"),
+                Some("unit".into())
+            );
+            // masked by the unit's drop-in with the same name
+            assert_eq!(unit_file.lookup_all("Container", "Label"), vec!["unit"]);
+        }
+
+        #[test]
+        fn merges_dropins_in_lexical_order_across_dirs() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let high = temp_dir.path().join("high");
+            let low = temp_dir.path().join("low");
+            let a = write(high.join("a.container"), "[Container]\nImage=main\n");
+            write(
+                high.join("a.container.d/20-b.conf"),
+                "[Container]\nLabel=b\n",
+            );
+            write(
+                low.join("a.container.d/10-a.conf"),
+                "[Container]\nLabel=a\n",
+            );
+            write(
+                low.join("a.container.d/30-c.conf"),
+                "[Container]\nLabel=c\n",
+            );
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&a).unwrap();
+            unit_file
+                .load_dropins_from([high.as_path(), low.as_path()])
+                .unwrap();
+
+            assert_eq!(
+                unit_file.lookup_all("Container", "Label"),
+                vec!["a", "b", "c"]
+            );
+        }
+
+        #[test]
+        fn higher_priority_dir_masks_dropins_with_same_name() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let high = temp_dir.path().join("high");
+            let low = temp_dir.path().join("low");
+            let a = write(high.join("a.container"), "[Container]\nImage=main\n");
+            write(
+                high.join("a.container.d/10-same.conf"),
+                "[Container]\nLabel=high\n",
+            );
+            write(
+                low.join("a.container.d/10-same.conf"),
+                "[Container]\nLabel=low\n",
+            );
+            write(
+                low.join("container.d/10-same.conf"),
+                "[Container]\nNetwork=low\n",
+            );
+            write(
+                high.join("container.d/10-same.conf"),
+                "[Container]\nNetwork=high\n",
+            );
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&a).unwrap();
+            unit_file
+                .load_dropins_from([high.as_path(), low.as_path()])
+                .unwrap();
+
+            assert_eq!(unit_file.lookup_all("Container", "Label"), vec!["high"]);
+            // type-wide drop-in is masked by the unit's drop-in with the same name
+            assert!(!unit_file.has_key("Container", "Network"));
+        }
+
+        #[test]
+        fn later_dropins_can_reset_list_keys() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let dir = temp_dir.path();
+            let a = write(
+                dir.join("a.container"),
+                "[Container]\nImage=main\nEnvironment=MAIN=main\n",
+            );
+            write(
+                dir.join("container.d/10-common.conf"),
+                "[Container]\nEnvironment=COMMON=common\n",
+            );
+            write(
+                dir.join("a.container.d/10-reset.conf"),
+                "[Container]\nEnvironment=\nEnvironment=RESET=reset\n",
+            );
+            write(
+                dir.join("a.container.d/20-add.conf"),
+                "[Container]\nEnvironment=ADDED=added\n",
+            );
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&a).unwrap();
+            unit_file.load_dropins_from([dir]).unwrap();
+
+            assert_eq!(
+                unit_file.lookup_all_key_val("Container", "Environment"),
+                vec![
+                    ("RESET".into(), "reset".into()),
+                    ("ADDED".into(), "added".into())
+                ]
+            );
+        }
+
+        #[test]
+        fn returns_merged_dropins_in_merge_order() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let dir = temp_dir.path();
+            let unit = write(dir.join("foo.container"), "[Container]\nImage=foo\n");
+            let unit_dropin = write(
+                dir.join("foo.container.d/10-unit.conf"),
+                "[Container]\nLabel=unit\n",
+            );
+            let type_dropin = write(
+                dir.join("container.d/20-type.conf"),
+                "[Container]\nLabel=type\n",
+            );
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&unit).unwrap();
+            let merged_paths = unit_file.load_dropins_from([dir]).unwrap();
+
+            assert_eq!(merged_paths, vec![type_dropin, unit_dropin]);
+        }
+
+        #[test]
+        fn unit_dropins_can_reset_lists_of_type_wide_dropins() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let dir = temp_dir.path();
+            let a = write(
+                dir.join("a.container"),
+                "[Container]\nImage=main\nVolume=/main:/main\n",
+            );
+            let b = write(
+                dir.join("b.container"),
+                "[Container]\nImage=main\nVolume=/main:/main\n",
+            );
+            write(
+                dir.join("container.d/10-common.conf"),
+                "[Container]\nVolume=/common:/common\n",
+            );
+            write(
+                dir.join("a.container.d/20-reset.conf"),
+                "[Container]\nVolume=\nVolume=/own:/own\n",
+            );
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&a).unwrap();
+            unit_file.load_dropins_from([dir]).unwrap();
+            assert_eq!(
+                unit_file.lookup_all("Container", "Volume"),
+                vec!["/own:/own"]
+            );
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&b).unwrap();
+            unit_file.load_dropins_from([dir]).unwrap();
+            assert_eq!(
+                unit_file.lookup_all("Container", "Volume"),
+                vec!["/main:/main", "/common:/common"]
+            );
+        }
+
+        #[test]
+        fn ignores_nested_dirs() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let dir = temp_dir.path();
+            let a = write(dir.join("a.container"), "[Container]\nImage=main\n");
+            write(
+                dir.join("a.container.d/nested/10-nested.conf"),
+                "[Container]\nLabel=nested\n",
+            );
+            fs::create_dir_all(dir.join("a.container.d/20-dir.conf")).unwrap();
+
+            let mut unit_file = SystemdUnitFile::load_from_path(&a).unwrap();
+            unit_file.load_dropins_from([dir]).unwrap();
+
+            assert!(!unit_file.has_key("Container", "Label"));
+        }
+    }
+
+    mod load_from_path {
+        use super::*;
+
+        #[test]
+        fn fails_with_not_found_for_missing_file() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+
+            let err = SystemdUnitFile::load_from_path(&temp_dir.path().join("missing.container"))
+                .unwrap_err();
+
+            assert!(matches!(err, IoError::Io(_)));
+            assert_eq!(err.kind(), Some(io::ErrorKind::NotFound));
+            assert!(err.is_not_found());
+        }
+
+        #[test]
+        fn fails_with_parse_error_for_invalid_file() {
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let path = temp_dir.path().join("broken.container");
+            fs::write(&path, "[Container]\nImage=foo\n[Broken\n").unwrap();
+
+            let err = SystemdUnitFile::load_from_path(&path).unwrap_err();
+
+            assert!(matches!(
+                err,
+                IoError::Unit(crate::systemd_unit::Error::UnitFile(..))
+            ));
+            assert_eq!(err.kind(), None);
+            assert!(!err.is_not_found());
+        }
+
+        #[cfg(feature = "gzip")]
+        #[test]
+        fn loads_gzip_compressed_unit() {
+            use std::io::Write;
+
+            let temp_dir = tempfile::tempdir().expect("cannot create temp dir");
+            let path = temp_dir.path().join("foo.container.gz");
+
+            let mut encoder = flate2::write::GzEncoder::new(
+                fs::File::create(&path).expect("cannot create file"),
+                flate2::Compression::default(),
+            );
+            encoder
+                .write_all(b"[Container]\nImage=quay.io/podman/hello\n")
+                .expect("cannot write file");
+            encoder.finish().expect("cannot write file");
+
+            let unit_file = SystemdUnitFile::load_from_path(&path).unwrap();
+
+            assert_eq!(unit_file.path(), &temp_dir.path().join("foo.container"));
+            assert_eq!(
+                unit_file.lookup("Container", "Image"),
+                Some("quay.io/podman/hello".into())
+            );
+        }
+    }
+
+    mod load_from_str {
+        use super::*;
+
+        #[test]
+        fn keeps_path_and_parses_data() {
+            let unit_file =
+                SystemdUnitFile::load_from_str(Path::new("stdin.container"), "[Foo]\nBar=baz\n")
+                    .unwrap();
+
+            assert_eq!(unit_file.path(), &PathBuf::from("stdin.container"));
+            assert_eq!(unit_file.lookup("Foo", "Bar"), Some("baz".into()));
+        }
+
+        #[test]
+        fn parse_error_includes_path() {
+            let err = SystemdUnitFile::load_from_str(
+                Path::new("/etc/containers/systemd/foo.container"),
+                "[Foo]\nBar=baz\n[Broken\n",
+            )
+            .unwrap_err();
+
+            assert!(matches!(err, crate::systemd_unit::Error::UnitFile(..)));
+            assert!(err
+                .to_string()
+                .starts_with("failed to parse unit file /etc/containers/systemd/foo.container:3:"));
+        }
+    }
+
+    mod uncompressed_path {
+        use super::*;
+
+        #[test]
+        fn keeps_uncompressed_path() {
+            assert_eq!(
+                uncompressed_path(Path::new("foo/bar.container")),
+                PathBuf::from("foo/bar.container")
+            );
+        }
+
+        #[test]
+        #[cfg(feature = "gzip")]
+        fn strips_gz_extension() {
+            assert_eq!(
+                uncompressed_path(Path::new("foo/bar.container.gz")),
+                PathBuf::from("foo/bar.container")
+            );
+        }
+
+        #[test]
+        #[cfg(not(feature = "gzip"))]
+        fn keeps_gz_extension_without_gzip_feature() {
+            assert_eq!(
+                uncompressed_path(Path::new("foo/bar.container.gz")),
+                PathBuf::from("foo/bar.container.gz")
+            );
+        }
+    }
+
     mod unit_type {
         use super::*;
 
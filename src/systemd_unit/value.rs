@@ -1,5 +1,6 @@
 use super::{parse_bool, quote_value, unquote_value, Error};
 use ordered_multimap::ListOrderedMultimap;
+use std::fmt;
 use std::str::FromStr;
 use std::sync::OnceLock;
 
@@ -11,7 +12,7 @@ pub(crate) struct Entries {
 impl Default for &Entries {
     fn default() -> Self {
         static EMPTY: OnceLock<Entries> = OnceLock::new();
-        EMPTY.get_or_init(|| Entries::default())
+        EMPTY.get_or_init(Entries::default)
     }
 }
 
@@ -19,9 +20,12 @@ pub(crate) type EntryKey = String;
 
 pub(crate) type EntryRawValue = String;
 
-#[derive(Clone, Default, Debug, PartialEq)]
+#[derive(Clone, Default, PartialEq)]
 pub struct EntryValue(EntryRawValue);
 
+// Longer values (e.g. embedded scripts) are elided in debug output, so they don't drown the rest
+const MAX_DEBUG_VALUE_CHARS: usize = 64;
+
 impl EntryValue {
     pub fn from_raw<S: Into<String>>(raw: S) -> Self {
         Self::try_from_raw(raw).expect("value not correctly quoted")
@@ -86,9 +90,20 @@ impl FromStr for EntryValue {
     }
 }
 
-impl ToString for EntryValue {
-    fn to_string(&self) -> String {
-        self.unquote()
+impl fmt::Debug for EntryValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.chars().count() <= MAX_DEBUG_VALUE_CHARS {
+            return f.debug_tuple("EntryValue").field(&self.0).finish();
+        }
+
+        let prefix: String = self.0.chars().take(MAX_DEBUG_VALUE_CHARS).collect();
+        write!(f, "EntryValue({prefix:?}... ({} bytes))", self.0.len())
+    }
+}
+
+impl fmt::Display for EntryValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.unquote())
     }
 }
 
@@ -212,6 +227,27 @@ mod tests {
         }
     }
 
+    mod debug_for_entry_value {
+        use super::*;
+
+        #[test]
+        fn shows_short_value() {
+            let value = EntryValue::from_raw("foo bar");
+
+            assert_eq!(format!("{value:?}"), "EntryValue(\"foo bar\")");
+        }
+
+        #[test]
+        fn elides_long_value() {
+            let value = EntryValue::from_raw("ä".repeat(100));
+
+            assert_eq!(
+                format!("{value:?}"),
+                format!("EntryValue({:?}... (200 bytes))", "ä".repeat(64))
+            );
+        }
+    }
+
     mod from_ref_str_for_entry_value {
         use super::*;
 